@@ -0,0 +1,131 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use reqwest::{Request, Response, header::CONTENT_TYPE};
+
+use crate::{
+    api::{
+        client::{ALBUM_GET, ARTIST_PAGE, ARTIST_RELEASES, LABEL_GET},
+        models::AlbumPage,
+    },
+    data::{
+        self,
+        db::Db,
+        http_cache::{self, CachedResponse},
+    },
+};
+
+/// How long responses stay fresh for the endpoints that are cached by
+/// default. Other endpoints aren't cached.
+const DEFAULT_TTLS: [(&str, Duration); 4] = [
+    (ARTIST_PAGE, Duration::from_hours(1)),
+    (ARTIST_RELEASES, Duration::from_hours(1)),
+    (ALBUM_GET, Duration::from_hours(24)),
+    (LABEL_GET, Duration::from_hours(1)),
+];
+
+/// A cache of catalog responses, stored in a `SQLite` database.
+///
+/// Fresh responses are served without asking Qobuz. Once a response goes
+/// stale it's revalidated with a conditional request when Qobuz sent an
+/// `ETag` or `Last-Modified` header, and fetched again otherwise.
+#[derive(Debug)]
+pub struct ResponseCache {
+    db: Mutex<Db>,
+    ttls: HashMap<String, Duration>,
+}
+
+impl ResponseCache {
+    /// Opens the cache in the database at `db_path`. TTLs can be changed
+    /// with `QOBUZ_CACHE_TTL_<ENDPOINT>` variables (in seconds, `0` to
+    /// always revalidate), where the endpoint is uppercase with `/` replaced
+    /// by `_`, e.g. `QOBUZ_CACHE_TTL_ARTIST_PAGE`.
+    /// # Errors
+    /// Will return `Err` if the database can't be opened or a TTL isn't a
+    /// number.
+    pub fn open(db_path: &str) -> anyhow::Result<Self> {
+        let db = Db::new(db_path)?;
+        data::db::init(&db)?;
+
+        let mut ttls = HashMap::new();
+        for (endpoint, default_ttl) in DEFAULT_TTLS {
+            let var = format!(
+                "QOBUZ_CACHE_TTL_{}",
+                endpoint.to_uppercase().replace('/', "_")
+            );
+            let ttl = match std::env::var(var) {
+                Ok(secs) => Duration::from_secs(secs.parse()?),
+                Err(_) => default_ttl,
+            };
+            ttls.insert(endpoint.to_string(), ttl);
+        }
+
+        Ok(Self {
+            db: Mutex::new(db),
+            ttls,
+        })
+    }
+
+    /// Caches an endpoint's responses for `ttl`.
+    #[must_use]
+    pub fn with_ttl(mut self, endpoint: &str, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint.to_string(), ttl);
+        self
+    }
+
+    /// How long the endpoint's responses stay fresh, if they're cached.
+    pub(crate) fn ttl(&self, endpoint: &str) -> Option<Duration> {
+        self.ttls.get(endpoint).copied()
+    }
+
+    pub(crate) fn get(&self, key: &str) -> anyhow::Result<Option<CachedResponse>> {
+        http_cache::get(&self.db.lock().unwrap(), key)
+    }
+
+    pub(crate) fn put(&self, response: &CachedResponse) -> anyhow::Result<()> {
+        http_cache::put(&self.db.lock().unwrap(), response)
+    }
+
+    pub(crate) fn touch(&self, key: &str) -> anyhow::Result<()> {
+        http_cache::touch(&self.db.lock().unwrap(), key, now())
+    }
+}
+
+/// Whether a successful response is worth keeping. Releases that can't be
+/// streamed yet, or have no tracks, are fetched again until they're ready,
+/// so retrying them doesn't keep seeing the same answer.
+pub(crate) fn is_cacheable(endpoint: &str, body: &[u8]) -> bool {
+    if endpoint != ALBUM_GET {
+        return true;
+    }
+    serde_json::from_slice::<AlbumPage>(body)
+        .is_ok_and(|album| album.streamable != Some(false) && !album.tracks.items.is_empty())
+}
+
+/// The cache key for a request: its endpoint and sorted query.
+pub(crate) fn key(endpoint: &str, request: &Request) -> String {
+    let mut query = request
+        .url()
+        .query_pairs()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>();
+    query.sort();
+    format!("{endpoint}?{}", query.join("&"))
+}
+
+pub(crate) fn is_fresh(response: &CachedResponse, ttl: Duration) -> bool {
+    let age = now().saturating_sub(response.fetched_at);
+    u64::try_from(age).is_ok_and(|age| age < ttl.as_secs())
+}
+
+pub(crate) fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Turns a cached response back into a response.
+pub(crate) fn to_response(response: CachedResponse) -> anyhow::Result<Response> {
+    let mut builder = http::Response::builder().status(200);
+    if let Some(content_type) = response.content_type {
+        builder = builder.header(CONTENT_TYPE, content_type);
+    }
+    Ok(builder.body(response.body)?.into())
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use reqwest::{
+    Request, Response,
+    header::{CONTENT_TYPE, ETAG, LAST_MODIFIED, RETRY_AFTER},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::ApiError;
+
+const CASSETTE_FILE: &str = "cassette.json";
+const REDACTED: &str = "REDACTED";
+
+/// Query parameters, form fields and response fields that never make it into
+/// a cassette.
+const SECRETS: [&str; 6] = [
+    "app_id",
+    "email",
+    "login",
+    "password",
+    "user_auth_token",
+    "username",
+];
+
+/// Response headers worth keeping in a cassette.
+const KEPT_HEADERS: [reqwest::header::HeaderName; 4] =
+    [CONTENT_TYPE, ETAG, LAST_MODIFIED, RETRY_AFTER];
+
+/// Records API interactions to a cassette, or replays them from one.
+///
+/// Recording keeps every request and response, with credentials redacted,
+/// and writes them to `cassette.json` in a directory when the cassette is
+/// flushed or dropped. Replaying serves those responses without touching the
+/// network: each request gets the responses recorded for it in order, with
+/// the last one repeating.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(Mutex<Recording>),
+    Replay(Mutex<HashMap<String, VecDeque<RecordedResponse>>>),
+}
+
+#[derive(Debug)]
+struct Recording {
+    interactions: Vec<Interaction>,
+    /// Whether anything was recorded since the cassette was last written.
+    unsaved: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A [`CassetteFile`] to be written, borrowing the recorded interactions.
+#[derive(Debug, Serialize)]
+struct CassetteFileRef<'a> {
+    interactions: &'a [Interaction],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    endpoint: String,
+    query: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    form: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: RecordedBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RecordedBody {
+    Json(serde_json::Value),
+    Text(String),
+}
+
+impl Cassette {
+    /// Records interactions to `dir`, adding to any cassette already there.
+    /// # Errors
+    /// Will return `Err` if an existing cassette can't be read.
+    pub fn record_to(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = dir.as_ref().join(CASSETTE_FILE);
+        let interactions = if path.exists() {
+            read(&path)?.interactions
+        } else {
+            vec![]
+        };
+
+        Ok(Self {
+            path,
+            mode: Mode::Record(Mutex::new(Recording {
+                interactions,
+                unsaved: false,
+            })),
+        })
+    }
+
+    /// Replays interactions recorded in `dir`.
+    /// # Errors
+    /// Will return `Err` if the cassette can't be read.
+    pub fn replay_from(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = dir.as_ref().join(CASSETTE_FILE);
+        let mut responses = HashMap::<_, VecDeque<_>>::new();
+        for interaction in read(&path)?.interactions {
+            responses
+                .entry(interaction.request.key())
+                .or_default()
+                .push_back(interaction.response);
+        }
+
+        Ok(Self {
+            path,
+            mode: Mode::Replay(Mutex::new(responses)),
+        })
+    }
+
+    /// Records to `QOBUZ_RECORD_DIR` or replays from `QOBUZ_REPLAY_DIR`,
+    /// whichever is set.
+    /// # Errors
+    /// Will return `Err` if both are set or the cassette can't be read.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        match (
+            std::env::var("QOBUZ_RECORD_DIR"),
+            std::env::var("QOBUZ_REPLAY_DIR"),
+        ) {
+            (Ok(_), Ok(_)) => {
+                anyhow::bail!("only one of QOBUZ_RECORD_DIR and QOBUZ_REPLAY_DIR can be set")
+            }
+            (Ok(dir), Err(_)) => Ok(Some(Self::record_to(dir)?)),
+            (Err(_), Ok(dir)) => Ok(Some(Self::replay_from(dir)?)),
+            (Err(_), Err(_)) => Ok(None),
+        }
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    /// Serves the next recorded response for a request.
+    pub(crate) fn replay(&self, endpoint: &str, request: &Request) -> anyhow::Result<Response> {
+        let Mode::Replay(responses) = &self.mode else {
+            anyhow::bail!("the cassette isn't replaying");
+        };
+
+        let key = RecordedRequest::new(endpoint, request).key();
+        let mut responses = responses.lock().unwrap();
+        let queue = responses.get_mut(&key).with_context(|| {
+            format!("no response recorded in {} for {key}", self.path.display())
+        })?;
+        let response = if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        };
+
+        response
+            .with_context(|| format!("no response recorded in {} for {key}", self.path.display()))?
+            .into_response()
+    }
+
+    /// Records a response, returning an equivalent one to use in its place.
+    /// Does nothing unless the cassette is recording.
+    pub(crate) async fn record(
+        &self,
+        endpoint: &str,
+        request: &Request,
+        response: Response,
+    ) -> anyhow::Result<Response> {
+        let Mode::Record(recording) = &self.mode else {
+            return Ok(response);
+        };
+
+        let status = response.status().as_u16();
+        let headers = KEPT_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers().get(name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let body = response.bytes().await.map_err(|source| ApiError::Network {
+            endpoint: endpoint.to_string(),
+            source,
+        })?;
+        let body = String::from_utf8_lossy(&body).into_owned();
+
+        let recorded_body = match serde_json::from_str::<serde_json::Value>(&body) {
+            Ok(mut json) => {
+                redact(&mut json);
+                RecordedBody::Json(json)
+            }
+            Err(_) => RecordedBody::Text(body.clone()),
+        };
+        let interaction = Interaction {
+            request: RecordedRequest::new(endpoint, request),
+            response: RecordedResponse {
+                status,
+                headers: headers.clone(),
+                body: recorded_body,
+            },
+        };
+
+        {
+            let mut recording = recording.lock().unwrap();
+            recording.interactions.push(interaction);
+            recording.unsaved = true;
+        }
+
+        // The original response's body has been read, so hand back a copy.
+        RecordedResponse {
+            status,
+            headers,
+            body: RecordedBody::Text(body),
+        }
+        .into_response()
+    }
+
+    /// Writes what's been recorded to the cassette file. This happens when
+    /// the cassette is dropped too. Does nothing unless the cassette is
+    /// recording and something new was recorded.
+    /// # Errors
+    /// Will return `Err` if the cassette can't be written.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        let Mode::Record(recording) = &self.mode else {
+            return Ok(());
+        };
+        let mut recording = recording.lock().unwrap();
+        if !recording.unsaved {
+            return Ok(());
+        }
+
+        let json = serde_json::to_vec_pretty(&CassetteFileRef {
+            interactions: &recording.interactions,
+        })?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, json)
+            .with_context(|| format!("writing {}", self.path.display()))?;
+        recording.unsaved = false;
+        Ok(())
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!(error = ?e, "couldn't write the cassette");
+        }
+    }
+}
+
+impl RecordedRequest {
+    fn new(endpoint: &str, request: &Request) -> Self {
+        let query = redact_pairs(
+            request
+                .url()
+                .query_pairs()
+                .map(|(k, v)| (k.into_owned(), v.into_owned())),
+        );
+        let form = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .and_then(|body| serde_urlencoded::from_bytes::<Vec<(String, String)>>(body).ok())
+            .map(|form| redact_pairs(form.into_iter()))
+            .unwrap_or_default();
+
+        Self {
+            method: request.method().to_string(),
+            endpoint: endpoint.to_string(),
+            query,
+            form,
+        }
+    }
+
+    /// What a request is matched on when replaying.
+    fn key(&self) -> String {
+        let pairs = |pairs: &[(String, String)]| {
+            let mut pairs = pairs
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>();
+            pairs.sort();
+            pairs.join("&")
+        };
+        format!(
+            "{} {}?{} {}",
+            self.method,
+            self.endpoint,
+            pairs(&self.query),
+            pairs(&self.form)
+        )
+    }
+}
+
+impl RecordedResponse {
+    fn into_response(self) -> anyhow::Result<Response> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = match self.body {
+            RecordedBody::Json(json) => serde_json::to_string(&json)?,
+            RecordedBody::Text(text) => text,
+        };
+        Ok(builder.body(body)?.into())
+    }
+}
+
+fn read(path: &Path) -> anyhow::Result<CassetteFile> {
+    let cassette = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&cassette).with_context(|| format!("decoding {}", path.display()))
+}
+
+fn redact_pairs(pairs: impl Iterator<Item = (String, String)>) -> Vec<(String, String)> {
+    pairs
+        .map(|(k, v)| {
+            if SECRETS.contains(&k.as_str()) {
+                (k, REDACTED.to_string())
+            } else {
+                (k, v)
+            }
+        })
+        .collect()
+}
+
+/// Redacts secrets anywhere in a JSON response.
+fn redact(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if SECRETS.contains(&k.as_str()) && !v.is_null() {
+                    *v = REDACTED.into();
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
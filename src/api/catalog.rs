@@ -0,0 +1,64 @@
+use std::future::Future;
+
+use crate::{
+    api::models::{AlbumPage, ArtistList, ArtistPage, ArtistSummary, LabelPage, ReleaseList},
+    types::ReleaseType,
+};
+
+/// A source of Qobuz catalog data.
+///
+/// [`Api`](crate::api::Api) talks to Qobuz over HTTP, while
+/// [`InMemoryCatalog`](crate::api::InMemoryCatalog) serves canned data for
+/// tests and alternative sources.
+pub trait Catalog {
+    /// Gets an artist's page, which includes their releases.
+    fn get_artist_page(
+        &self,
+        artist_id: u32,
+    ) -> impl Future<Output = anyhow::Result<ArtistPage>> + Send;
+
+    /// Gets a page of an artist's releases of one type, for releases past
+    /// the ones included in their artist page.
+    fn get_artist_releases(
+        &self,
+        artist_id: u32,
+        release_type: ReleaseType,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Output = anyhow::Result<ReleaseList>> + Send;
+
+    /// Gets a label along with a page of its releases.
+    fn get_label(
+        &self,
+        label_id: u64,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Output = anyhow::Result<LabelPage>> + Send;
+
+    /// Searches for artists by name, best matches first.
+    fn search_artists(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> impl Future<Output = anyhow::Result<Vec<ArtistSummary>>> + Send;
+
+    /// Gets a page of the logged in user's favorite artists.
+    fn get_favorite_artists(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Output = anyhow::Result<ArtistList>> + Send;
+
+    /// Gets a release with all of its tracks, or `None` if it can't be found.
+    fn get_release(
+        &self,
+        release_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<AlbumPage>>> + Send;
+
+    /// Creates a private playlist with the given tracks, returning its id.
+    fn create_playlist(
+        &self,
+        name: &str,
+        track_ids: Vec<u32>,
+    ) -> impl Future<Output = anyhow::Result<u32>> + Send;
+}
@@ -1,76 +1,484 @@
 use anyhow::Context;
-use reqwest::header::HeaderMap;
+use reqwest::{
+    Method, Request, Response, StatusCode,
+    header::{CONTENT_TYPE, ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::de::DeserializeOwned;
+use tracing::Instrument;
 
-use crate::api::models::{AlbumPage, ArtistPage, NewPlaylist, Track, Tracks};
+use crate::api::{
+    ApiError, Catalog,
+    cache::{self, ResponseCache},
+    cassette::Cassette,
+    models::{
+        AlbumPage, ArtistList, ArtistPage, ArtistSummary, Artists, LabelPage, Login, NewPlaylist,
+        ReleaseList,
+    },
+    rate_limit::{RateLimit, RateLimiter},
+    retry::{self, RetryPolicy},
+};
+use crate::{
+    data::{credentials, db::Db, http_cache},
+    types::ReleaseType,
+};
 
 const USER_AGENT: &str = "QobuzBot/0.1 (+bbmusic@fastmail.com; API-access)";
 const DEFAULT_API_BASE: &str = "https://www.qobuz.com/api.json/0.2";
 
 // API Paths
-const ARTIST_PAGE: &str = "artist/page";
-const ALBUM_GET: &str = "album/get";
+pub(crate) const ARTIST_PAGE: &str = "artist/page";
+pub(crate) const ARTIST_RELEASES: &str = "artist/getReleasesList";
+pub(crate) const ALBUM_GET: &str = "album/get";
+pub(crate) const LABEL_GET: &str = "label/get";
+const CATALOG_SEARCH: &str = "catalog/search";
+const USER_FAVORITES: &str = "favorite/getUserFavorites";
+const USER_LOGIN: &str = "user/login";
+const PLAYLIST_CREATE: &str = "playlist/create";
+const PLAYLIST_ADD_TRACKS: &str = "playlist/addTracks";
 
+const TRACKS_PAGE_SIZE: usize = 50;
+
+#[allow(clippy::struct_field_names)]
 pub struct Api {
     api_base: String,
     client: reqwest::Client,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    cache: Option<ResponseCache>,
+    cassette: Option<Cassette>,
 }
 
 impl Api {
-    pub fn new(auth_token: &str, app_id: &str) -> anyhow::Result<Self> {
+    /// Creates a client for the given app id, authenticated with the auth
+    /// token if there is one. Without a token, only [`Api::login`] and
+    /// public catalog endpoints work.
+    /// # Errors
+    /// Will return `Err` if the credentials aren't valid header values, or
+    /// the retry policy, rate limit or cassette in the environment is
+    /// invalid.
+    pub fn new(auth_token: Option<&str>, app_id: &str) -> anyhow::Result<Self> {
         let api_base = std::env::var("QOBUZ_API_BASE").unwrap_or(DEFAULT_API_BASE.to_string());
-        let headers = HeaderMap::from_iter([
+        let mut headers = HeaderMap::from_iter([
             ("User-Agent".parse()?, USER_AGENT.parse()?),
-            ("X-User-Auth-Token".parse()?, auth_token.parse()?),
             ("X-App-Id".parse()?, app_id.parse()?),
         ]);
+        if let Some(auth_token) = auth_token {
+            headers.insert("X-User-Auth-Token", auth_token.parse()?);
+        }
 
         let client = reqwest::Client::builder()
             .cookie_store(true)
             .default_headers(headers)
             .build()?;
 
-        Ok(Self { api_base, client })
+        let retry_policy = RetryPolicy::from_env()?;
+        let rate_limiter = RateLimiter::new(RateLimit::from_env()?);
+        let cassette = Cassette::from_env()?;
+
+        Ok(Self {
+            api_base,
+            client,
+            retry_policy,
+            rate_limiter,
+            cache: None,
+            cassette,
+        })
+    }
+
+    /// Creates a client from the environment. The app id comes from
+    /// `QOBUZ_APP_ID`, and the auth token from `QOBUZ_AUTH_TOKEN` if it's
+    /// set, otherwise from the credentials stored in `db` by a login.
+    /// # Errors
+    /// Will return `Err` if `QOBUZ_APP_ID` isn't set, the stored credentials
+    /// can't be read, or [`Api::new`] fails.
+    pub fn from_env_and_db(db: &Db) -> anyhow::Result<Self> {
+        let app_id = std::env::var("QOBUZ_APP_ID").context("QOBUZ_APP_ID must be set")?;
+        let auth_token = match std::env::var("QOBUZ_AUTH_TOKEN") {
+            Ok(auth_token) => Some(auth_token),
+            Err(_) => credentials::get(db)?.map(|c| c.auth_token),
+        };
+        Self::new(auth_token.as_deref(), &app_id)
+    }
+
+    /// Uses a different base URL for the API.
+    #[must_use]
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into();
+        self
+    }
+
+    /// Uses a different policy for retrying failed requests.
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits how fast requests are sent. `None` sends them as fast as
+    /// possible.
+    #[must_use]
+    pub fn with_rate_limit(mut self, rate_limit: Option<RateLimit>) -> Self {
+        self.rate_limiter = RateLimiter::new(rate_limit);
+        self
     }
 
-    pub async fn get_artist_page(&self, artist_id: u32) -> anyhow::Result<ArtistPage> {
+    /// Caches catalog responses. `None` turns caching off.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Records requests to, or replays them from, a cassette. `None` talks
+    /// to Qobuz as usual.
+    #[must_use]
+    pub fn with_cassette(mut self, cassette: Option<Cassette>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
+    /// Logs in with an email address (or username) and password, returning
+    /// the user's auth token.
+    /// # Errors
+    /// Will return `Err` if the credentials are wrong or the request fails.
+    pub async fn login(&self, email: &str, password: &str) -> anyhow::Result<Login> {
+        // Qobuz expects the password to be MD5 hashed.
+        let password = format!("{:x}", md5::compute(password));
+        let request = self
+            .client
+            .get(format!("{}/{USER_LOGIN}", self.api_base))
+            .query(&[("email", email), ("password", password.as_str())])
+            .build()?;
+
+        let response = self.send(USER_LOGIN, request).await?;
+        Self::decode(USER_LOGIN, response).await
+    }
+
+    /// Sends a request, retrying transient failures according to the retry
+    /// policy. Once attempts run out, the last response is returned as-is.
+    /// If the server asks us to wait longer than the policy allows, the
+    /// request fails with [`ApiError::RateLimited`] straight away.
+    ///
+    /// Requests that aren't idempotent are only retried if they never made
+    /// it to the server.
+    #[tracing::instrument(skip(self, request), fields(method = %request.method()))]
+    async fn send(&self, endpoint: &str, request: Request) -> anyhow::Result<Response> {
+        let idempotent = request.method() == Method::GET;
+        let mut attempt = 1;
+        loop {
+            let req = request
+                .try_clone()
+                .context("request body can't be retried")?;
+            let span = tracing::info_span!("attempt", attempt, status = tracing::field::Empty);
+            let result = match &self.cassette {
+                Some(cassette) if cassette.is_replaying() => Ok(cassette.replay(endpoint, &req)?),
+                _ => {
+                    self.rate_limiter.acquire().instrument(span.clone()).await;
+                    self.client.execute(req).instrument(span.clone()).await
+                }
+            };
+            let result = match (&self.cassette, result) {
+                (Some(cassette), Ok(response)) => {
+                    Ok(cassette.record(endpoint, &request, response).await?)
+                }
+                (_, result) => result,
+            };
+
+            let out_of_attempts = attempt >= self.retry_policy.max_attempts;
+            let delay = match result {
+                Ok(response) => {
+                    span.record("status", response.status().as_u16());
+                    if out_of_attempts
+                        || !idempotent
+                        || !retry::is_retryable_status(response.status())
+                    {
+                        return Ok(response);
+                    }
+                    match retry::retry_after(&response) {
+                        Some(wait) if wait > self.retry_policy.max_retry_after => {
+                            return Err(ApiError::RateLimited {
+                                endpoint: endpoint.to_string(),
+                                retry_after: Some(wait),
+                            }
+                            .into());
+                        }
+                        Some(wait) => wait,
+                        None => self.retry_policy.backoff(attempt),
+                    }
+                }
+                Err(e) => {
+                    let transient =
+                        e.is_connect() || (idempotent && (e.is_timeout() || e.is_request()));
+                    if out_of_attempts || !transient {
+                        return Err(ApiError::Network {
+                            endpoint: endpoint.to_string(),
+                            source: e,
+                        }
+                        .into());
+                    }
+                    self.retry_policy.backoff(attempt)
+                }
+            };
+
+            tracing::warn!(attempt, ?delay, "retrying request");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request, serving it from the cache if there's a fresh response
+    /// for it, or revalidating a stale one.
+    async fn send_cached(&self, endpoint: &str, mut request: Request) -> anyhow::Result<Response> {
+        let Some((cache, ttl)) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| Some((cache, cache.ttl(endpoint)?)))
+        else {
+            return self.send(endpoint, request).await;
+        };
+
+        let key = cache::key(endpoint, &request);
+        let cached = cache.get(&key)?;
+        if let Some(cached) = &cached {
+            if cache::is_fresh(cached, ttl) {
+                tracing::debug!(key, "cache hit");
+                return cache::to_response(cached.clone());
+            }
+
+            let headers = request.headers_mut();
+            if let Some(etag) = &cached.etag {
+                headers.insert(IF_NONE_MATCH, etag.parse()?);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.parse()?);
+            }
+        }
+
+        let response = self.send(endpoint, request).await?;
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), cached) {
+            tracing::debug!(key, "cache revalidated");
+            cache.touch(&key)?;
+            return cache::to_response(cached);
+        }
+        if response.status() != StatusCode::OK {
+            return Ok(response);
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(ToString::to_string)
+        };
+        let content_type = header(CONTENT_TYPE);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.bytes().await.map_err(|source| ApiError::Network {
+            endpoint: endpoint.to_string(),
+            source,
+        })?;
+
+        let cached = http_cache::CachedResponse {
+            key,
+            endpoint: endpoint.to_string(),
+            body: body.to_vec(),
+            content_type,
+            etag,
+            last_modified,
+            fetched_at: cache::now(),
+        };
+        if cache::is_cacheable(endpoint, &cached.body) {
+            cache.put(&cached)?;
+        }
+        cache::to_response(cached)
+    }
+
+    /// Decodes a successful JSON response.
+    async fn decode<T: DeserializeOwned>(endpoint: &str, response: Response) -> anyhow::Result<T> {
+        let response = check_status(endpoint, response)?;
+        let body = response.bytes().await.map_err(|source| ApiError::Network {
+            endpoint: endpoint.to_string(),
+            source,
+        })?;
+        let decoded = serde_json::from_slice(&body).map_err(|source| ApiError::Decode {
+            endpoint: endpoint.to_string(),
+            source,
+        })?;
+        Ok(decoded)
+    }
+}
+
+/// Turns unsuccessful responses into an [`ApiError`].
+fn check_status(endpoint: &str, response: Response) -> Result<Response, ApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let endpoint = endpoint.to_string();
+    Err(match status {
+        StatusCode::UNAUTHORIZED => ApiError::Unauthorized { endpoint },
+        StatusCode::NOT_FOUND => ApiError::NotFound { endpoint },
+        StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited {
+            endpoint,
+            retry_after: retry::retry_after(&response),
+        },
+        status if status.is_server_error() => ApiError::Server { endpoint, status },
+        status => ApiError::UnexpectedStatus { endpoint, status },
+    })
+}
+
+impl Catalog for Api {
+    async fn get_artist_page(&self, artist_id: u32) -> anyhow::Result<ArtistPage> {
         let request = self
             .client
             .get(format!("{}/{ARTIST_PAGE}", self.api_base))
-            .query(&[("artist_id", artist_id.to_string())]);
+            .query(&[("artist_id", artist_id.to_string())])
+            .build()?;
 
-        let response = request.send().await?.json::<ArtistPage>().await?;
-        Ok(response)
+        let response = self.send_cached(ARTIST_PAGE, request).await?;
+        Self::decode(ARTIST_PAGE, response).await
     }
 
-    pub async fn get_release_tracks(&self, release_id: &str) -> anyhow::Result<Vec<Track>> {
+    async fn get_artist_releases(
+        &self,
+        artist_id: u32,
+        release_type: ReleaseType,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<ReleaseList> {
         let query = &[
-            ("album_id", release_id),
-            ("offset", "0"),
-            ("limit", "50"),
-            ("extra", "track_ids"),
+            ("artist_id", artist_id.to_string()),
+            ("release_type", release_type.to_api_str().to_string()),
+            ("offset", offset.to_string()),
+            ("limit", limit.to_string()),
+            ("sort", "release_date".to_string()),
         ];
 
         let request = self
             .client
-            .get(format!("{}/{ALBUM_GET}", self.api_base))
-            .query(query);
+            .get(format!("{}/{ARTIST_RELEASES}", self.api_base))
+            .query(query)
+            .build()?;
 
-        let response = request.send().await?;
+        let response = self.send_cached(ARTIST_RELEASES, request).await?;
+        Self::decode(ARTIST_RELEASES, response).await
+    }
 
-        if response.status() == reqwest::StatusCode::NOT_FOUND {
-            // Sometimes Qobuz makes an album that they themselves cannot find.
-            return Ok(vec![]);
-        }
+    async fn get_label(
+        &self,
+        label_id: u64,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<LabelPage> {
+        let query = &[
+            ("label_id", label_id.to_string()),
+            ("extra", "albums".to_string()),
+            ("offset", offset.to_string()),
+            ("limit", limit.to_string()),
+        ];
+
+        let request = self
+            .client
+            .get(format!("{}/{LABEL_GET}", self.api_base))
+            .query(query)
+            .build()?;
+
+        let response = self.send_cached(LABEL_GET, request).await?;
+        Self::decode(LABEL_GET, response).await
+    }
+
+    async fn search_artists(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ArtistSummary>> {
+        let query = &[
+            ("query", query.to_string()),
+            ("type", "artists".to_string()),
+            ("limit", limit.to_string()),
+        ];
+
+        let request = self
+            .client
+            .get(format!("{}/{CATALOG_SEARCH}", self.api_base))
+            .query(query)
+            .build()?;
+
+        let response = self.send(CATALOG_SEARCH, request).await?;
+        let Artists { artists } = Self::decode(CATALOG_SEARCH, response).await?;
+        Ok(artists.items)
+    }
+
+    async fn get_favorite_artists(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<ArtistList> {
+        let query = &[
+            ("type", "artists".to_string()),
+            ("offset", offset.to_string()),
+            ("limit", limit.to_string()),
+        ];
+
+        let request = self
+            .client
+            .get(format!("{}/{USER_FAVORITES}", self.api_base))
+            .query(query)
+            .build()?;
+
+        let response = self.send(USER_FAVORITES, request).await?;
+        let Artists { artists } = Self::decode(USER_FAVORITES, response).await?;
+        Ok(artists)
+    }
+
+    async fn get_release(&self, release_id: &str) -> anyhow::Result<Option<AlbumPage>> {
+        let mut release: Option<AlbumPage> = None;
+        loop {
+            let offset = release
+                .as_ref()
+                .map_or(0, |r| r.tracks.items.len())
+                .to_string();
+            let limit = TRACKS_PAGE_SIZE.to_string();
+            let query = &[
+                ("album_id", release_id),
+                ("offset", offset.as_str()),
+                ("limit", limit.as_str()),
+                ("extra", "track_ids"),
+            ];
+
+            let request = self
+                .client
+                .get(format!("{}/{ALBUM_GET}", self.api_base))
+                .query(query)
+                .build()?;
 
-        let response = response.json::<serde_json::Value>().await?;
-        let AlbumPage { tracks } =
-            serde_json::from_value::<AlbumPage>(response).context("decoding album page")?;
-        let Tracks { items } = tracks;
+            let response = self.send_cached(ALBUM_GET, request).await?;
 
-        Ok(items)
+            if response.status() == StatusCode::NOT_FOUND {
+                // Sometimes Qobuz makes an album that they themselves cannot find.
+                return Ok(None);
+            }
+
+            let page: AlbumPage = Self::decode(ALBUM_GET, response).await?;
+            let done = page.tracks.items.is_empty();
+            let album = match release.take() {
+                Some(mut album) => {
+                    album.tracks.items.extend(page.tracks.items);
+                    album
+                }
+                None => page,
+            };
+
+            if done || album.tracks.items.len() >= album.tracks.total {
+                return Ok(Some(album));
+            }
+            release = Some(album);
+        }
     }
 
-    pub async fn create_playlist(&self, name: &str, track_ids: Vec<u32>) -> anyhow::Result<u32> {
+    async fn create_playlist(&self, name: &str, track_ids: Vec<u32>) -> anyhow::Result<u32> {
         let form = [
             ("name", name),
             ("description", ""),
@@ -79,10 +487,12 @@ impl Api {
         ];
         let request = self
             .client
-            .post(format!("{}/playlist/create", self.api_base))
-            .form(&form);
+            .post(format!("{}/{PLAYLIST_CREATE}", self.api_base))
+            .form(&form)
+            .build()?;
 
-        let NewPlaylist { id } = request.send().await?.json::<NewPlaylist>().await?;
+        let response = self.send(PLAYLIST_CREATE, request).await?;
+        let NewPlaylist { id } = Self::decode(PLAYLIST_CREATE, response).await?;
         let playlist_id = id.to_string();
 
         let track_ids = track_ids
@@ -97,10 +507,12 @@ impl Api {
         ];
         let request = self
             .client
-            .post(format!("{}/playlist/addTracks", self.api_base))
-            .form(&form);
+            .post(format!("{}/{PLAYLIST_ADD_TRACKS}", self.api_base))
+            .form(&form)
+            .build()?;
 
-        request.send().await?;
+        let response = self.send(PLAYLIST_ADD_TRACKS, request).await?;
+        check_status(PLAYLIST_ADD_TRACKS, response)?;
 
         Ok(id)
     }
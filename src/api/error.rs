@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+/// Why a request to Qobuz failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    /// Qobuz rejected the credentials, usually because the auth token
+    /// expired.
+    #[error("not authorized to call {endpoint}; the auth token may have expired")]
+    Unauthorized { endpoint: String },
+    /// What was asked for doesn't exist, like an artist id with no artist.
+    #[error("{endpoint} found nothing")]
+    NotFound { endpoint: String },
+    /// Qobuz is throttling us, even after retrying.
+    #[error("rate limited calling {endpoint}")]
+    RateLimited {
+        endpoint: String,
+        retry_after: Option<Duration>,
+    },
+    /// Qobuz had a problem, even after retrying.
+    #[error("server error {status} calling {endpoint}")]
+    Server {
+        endpoint: String,
+        status: StatusCode,
+    },
+    /// Any other status that isn't a success. There's no exit code of its
+    /// own for these, so they exit like any other error.
+    #[error("unexpected status {status} calling {endpoint}")]
+    UnexpectedStatus {
+        endpoint: String,
+        status: StatusCode,
+    },
+    /// The request never got a response.
+    #[error("network error calling {endpoint}")]
+    Network {
+        endpoint: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// The response wasn't what we expected.
+    #[error("couldn't decode the response from {endpoint}")]
+    Decode {
+        endpoint: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl ApiError {
+    /// The process exit code for this error, so scripts can tell failures
+    /// apart. See the README for the full list.
+    #[must_use]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Unauthorized { .. } => 3,
+            Self::NotFound { .. } => 4,
+            Self::RateLimited { .. } => 5,
+            Self::Server { .. } => 6,
+            Self::Network { .. } => 7,
+            Self::Decode { .. } => 8,
+            Self::UnexpectedStatus { .. } => 1,
+        }
+    }
+}
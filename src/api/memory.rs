@@ -0,0 +1,225 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::{
+    api::{
+        Catalog,
+        models::{
+            AlbumList, AlbumPage, ArtistList, ArtistPage, ArtistSummary, LabelPage, Release,
+            ReleaseList,
+        },
+    },
+    types::ReleaseType,
+};
+
+/// A playlist created through an [`InMemoryCatalog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedPlaylist {
+    pub id: u32,
+    pub name: String,
+    pub track_ids: Vec<u32>,
+}
+
+/// A [`Catalog`] backed by data held in memory.
+///
+/// Artist pages are served in the order they were added, with the last page
+/// for an artist repeating once the others have been served. This makes it
+/// easy to simulate an artist putting out new releases between checks.
+#[derive(Debug, Default)]
+pub struct InMemoryCatalog {
+    artist_pages: Mutex<HashMap<u32, VecDeque<ArtistPage>>>,
+    artist_releases: Mutex<HashMap<(u32, ReleaseType), Vec<Release>>>,
+    releases: Mutex<HashMap<String, AlbumPage>>,
+    favorite_artists: Mutex<Vec<ArtistSummary>>,
+    labels: Mutex<HashMap<u64, (String, Vec<AlbumPage>)>>,
+    playlists: Mutex<Vec<CreatedPlaylist>>,
+}
+
+impl InMemoryCatalog {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a page for an artist, served after any pages already added.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn add_artist_page(&self, page: ArtistPage) {
+        self.artist_pages
+            .lock()
+            .unwrap()
+            .entry(page.id)
+            .or_default()
+            .push_back(page);
+    }
+
+    /// Sets all of an artist's releases of one type, which are served a page
+    /// at a time.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn add_artist_releases(
+        &self,
+        artist_id: u32,
+        release_type: ReleaseType,
+        releases: Vec<Release>,
+    ) {
+        self.artist_releases
+            .lock()
+            .unwrap()
+            .insert((artist_id, release_type), releases);
+    }
+
+    /// Adds a release along with its tracks.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn add_release(&self, release: AlbumPage) {
+        self.releases
+            .lock()
+            .unwrap()
+            .insert(release.id.clone(), release);
+    }
+
+    /// Sets a label's name and all of its releases, which are served a page
+    /// at a time.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn set_label(&self, label_id: u64, name: &str, releases: Vec<AlbumPage>) {
+        self.labels
+            .lock()
+            .unwrap()
+            .insert(label_id, (name.to_string(), releases));
+    }
+
+    /// Sets the user's favorite artists, which are served a page at a time.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    pub fn set_favorite_artists(&self, artists: Vec<ArtistSummary>) {
+        *self.favorite_artists.lock().unwrap() = artists;
+    }
+
+    /// All the playlists created so far.
+    /// # Panics
+    /// Will panic if the lock is poisoned.
+    #[must_use]
+    pub fn playlists(&self) -> Vec<CreatedPlaylist> {
+        self.playlists.lock().unwrap().clone()
+    }
+}
+
+impl Catalog for InMemoryCatalog {
+    async fn get_artist_page(&self, artist_id: u32) -> anyhow::Result<ArtistPage> {
+        let mut artist_pages = self.artist_pages.lock().unwrap();
+        let pages = artist_pages
+            .get_mut(&artist_id)
+            .ok_or_else(|| anyhow::anyhow!("artist {artist_id} not found"))?;
+
+        let page = if pages.len() > 1 {
+            pages.pop_front()
+        } else {
+            pages.front().cloned()
+        };
+
+        page.ok_or_else(|| anyhow::anyhow!("artist {artist_id} not found"))
+    }
+
+    async fn get_artist_releases(
+        &self,
+        artist_id: u32,
+        release_type: ReleaseType,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<ReleaseList> {
+        let artist_releases = self.artist_releases.lock().unwrap();
+        let releases = artist_releases
+            .get(&(artist_id, release_type))
+            .map_or(&[][..], Vec::as_slice);
+
+        let items = releases.iter().skip(offset).take(limit).cloned().collect();
+        Ok(ReleaseList {
+            has_more: offset + limit < releases.len(),
+            items,
+        })
+    }
+
+    async fn get_label(
+        &self,
+        label_id: u64,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<LabelPage> {
+        let labels = self.labels.lock().unwrap();
+        let (name, releases) = labels
+            .get(&label_id)
+            .ok_or_else(|| anyhow::anyhow!("label {label_id} not found"))?;
+        Ok(LabelPage {
+            id: label_id,
+            name: name.clone(),
+            albums: AlbumList {
+                total: releases.len(),
+                items: releases.iter().skip(offset).take(limit).cloned().collect(),
+            },
+        })
+    }
+
+    /// Finds artists whose name contains the query, ignoring case.
+    async fn search_artists(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ArtistSummary>> {
+        let query = query.to_lowercase();
+        let artist_pages = self.artist_pages.lock().unwrap();
+        let mut artists = artist_pages
+            .values()
+            .filter_map(VecDeque::front)
+            .filter(|page| page.name.display.to_lowercase().contains(&query))
+            .map(|page| ArtistSummary {
+                id: page.id,
+                name: page.name.display.clone(),
+                albums_count: u32::try_from(
+                    page.releases.iter().map(|r| r.items.len()).sum::<usize>(),
+                )
+                .ok(),
+                genre: None,
+            })
+            .collect::<Vec<_>>();
+        artists.sort_by_key(|artist| artist.id);
+        artists.truncate(limit);
+        Ok(artists)
+    }
+
+    async fn get_favorite_artists(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<ArtistList> {
+        let favorite_artists = self.favorite_artists.lock().unwrap();
+        Ok(ArtistList {
+            total: favorite_artists.len(),
+            items: favorite_artists
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    async fn get_release(&self, release_id: &str) -> anyhow::Result<Option<AlbumPage>> {
+        let releases = self.releases.lock().unwrap();
+        Ok(releases.get(release_id).cloned())
+    }
+
+    async fn create_playlist(&self, name: &str, track_ids: Vec<u32>) -> anyhow::Result<u32> {
+        let mut playlists = self.playlists.lock().unwrap();
+        let id = u32::try_from(playlists.len())? + 1;
+        playlists.push(CreatedPlaylist {
+            id,
+            name: name.to_string(),
+            track_ids,
+        });
+        Ok(id)
+    }
+}
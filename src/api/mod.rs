@@ -1,4 +1,18 @@
+mod cache;
+mod cassette;
+mod catalog;
 mod client;
+mod error;
+mod memory;
 pub mod models;
+mod rate_limit;
+mod retry;
 
+pub use cache::ResponseCache;
+pub use cassette::Cassette;
+pub use catalog::Catalog;
 pub use client::Api;
+pub use error::ApiError;
+pub use memory::{CreatedPlaylist, InMemoryCatalog};
+pub use rate_limit::RateLimit;
+pub use retry::RetryPolicy;
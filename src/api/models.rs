@@ -2,14 +2,28 @@ use serde::Deserialize;
 
 use crate::types::ReleaseType;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ArtistPage {
     pub id: u32,
     pub name: ArtistName,
     pub releases: Vec<ArtistRelease>,
+    /// Artists Qobuz thinks are like this one.
+    #[serde(default)]
+    pub similar_artists: SimilarArtists,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SimilarArtists {
+    pub items: Vec<SimilarArtist>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SimilarArtist {
+    pub id: u32,
+    pub name: ArtistName,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ArtistName {
     pub display: String,
 }
@@ -18,13 +32,91 @@ pub struct ArtistName {
 pub struct ArtistRelease {
     #[serde(rename(deserialize = "type"))]
     pub release_type: ReleaseType,
+    /// Whether the artist has more releases of this type than are in
+    /// `items`.
+    #[serde(default)]
+    pub has_more: bool,
     pub items: Vec<Release>,
 }
 
+/// A page of an artist's releases of one type.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseList {
+    pub has_more: bool,
+    pub items: Vec<Release>,
+}
+
+/// A release as listed on an artist page.
 #[derive(Deserialize, Debug, Clone)]
 pub struct Release {
     pub id: String,
     pub title: String,
+    #[serde(default)]
+    pub tracks_count: Option<u32>,
+    #[serde(default)]
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub parental_warning: Option<bool>,
+    #[serde(default)]
+    pub image: Option<Image>,
+    #[serde(default)]
+    pub label: Option<Label>,
+    #[serde(default)]
+    pub genre: Option<Genre>,
+    #[serde(default)]
+    pub dates: Option<ReleaseDates>,
+    #[serde(default)]
+    pub audio_info: Option<AudioInfo>,
+    #[serde(default)]
+    pub rights: Option<Rights>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Image {
+    pub small: Option<String>,
+    pub thumbnail: Option<String>,
+    pub large: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Label {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Genre {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseDates {
+    pub original: Option<String>,
+    pub stream: Option<String>,
+    pub download: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Rights {
+    pub streamable: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AudioInfo {
+    pub maximum_bit_depth: Option<u32>,
+    pub maximum_sampling_rate: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Login {
+    pub user_auth_token: String,
+    pub user: User,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub id: u64,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -32,13 +124,98 @@ pub struct NewPlaylist {
     pub id: u32,
 }
 
+/// A response listing artists, from `catalog/search` or
+/// `favorite/getUserFavorites`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Artists {
+    pub artists: ArtistList,
+}
+
+/// A page of artists.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArtistList {
+    /// How many artists there are across all pages.
+    pub total: usize,
+    pub items: Vec<ArtistSummary>,
+}
+
+/// An artist as listed in search results and favorites.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArtistSummary {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub albums_count: Option<u32>,
+    #[serde(default)]
+    pub genre: Option<Genre>,
+}
+
+/// A label as returned by `label/get`, with a page of its releases.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LabelPage {
+    pub id: u64,
+    pub name: String,
+    pub albums: AlbumList,
+}
+
+/// A page of releases.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlbumList {
+    /// How many releases there are across all pages.
+    pub total: usize,
+    pub items: Vec<AlbumPage>,
+}
+
+/// A release as returned by `album/get`.
 #[derive(Deserialize, Debug, Clone)]
 pub struct AlbumPage {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub release_date_original: Option<String>,
+    /// When the release came out, as a unix timestamp.
+    #[serde(default)]
+    pub released_at: Option<i64>,
+    #[serde(default)]
+    pub upc: Option<String>,
+    #[serde(default)]
+    pub label: Option<Label>,
+    #[serde(default)]
+    pub genre: Option<Genre>,
+    #[serde(default)]
+    pub tracks_count: Option<u32>,
+    #[serde(default)]
+    pub duration: Option<u32>,
+    #[serde(default)]
+    pub maximum_bit_depth: Option<u32>,
+    #[serde(default)]
+    pub maximum_sampling_rate: Option<f64>,
+    #[serde(default)]
+    pub parental_warning: Option<bool>,
+    #[serde(default)]
+    pub image: Option<Image>,
+    #[serde(default)]
+    pub streamable: Option<bool>,
+    #[serde(default)]
+    pub release_date_stream: Option<String>,
+    /// When the release can be streamed, as a unix timestamp.
+    #[serde(default)]
+    pub streamable_at: Option<i64>,
+    /// Only filled in by `album/get`. Releases listed elsewhere, like on a
+    /// label, come without tracks.
+    #[serde(default)]
     pub tracks: Tracks,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Tracks {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: usize,
+    /// How many tracks the release has, across all pages.
+    #[serde(default)]
+    pub total: usize,
     pub items: Vec<Track>,
 }
 
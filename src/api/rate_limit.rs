@@ -0,0 +1,116 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+const DEFAULT_BURST: u32 = 10;
+/// Slower rates wait out a quarter of an hour or more between requests, and
+/// tiny ones longer than a `Duration` can hold.
+const MIN_REQUESTS_PER_SECOND: f64 = 0.001;
+
+/// How many requests can be sent to Qobuz over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// The sustained request rate.
+    pub requests_per_second: f64,
+    /// How many requests can be sent at once after a quiet period.
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            burst: DEFAULT_BURST,
+        }
+    }
+}
+
+impl RateLimit {
+    /// Reads the limit from `QOBUZ_RATE_LIMIT` (requests per second) and
+    /// `QOBUZ_RATE_BURST`, using the defaults for anything that isn't set.
+    /// A rate of `0` turns rate limiting off.
+    /// # Errors
+    /// Will return `Err` if a variable isn't a number, or the rate isn't
+    /// finite or is too slow to wait out.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let mut limit = Self::default();
+        if let Ok(requests_per_second) = std::env::var("QOBUZ_RATE_LIMIT") {
+            limit.requests_per_second = requests_per_second.parse()?;
+            if !limit.requests_per_second.is_finite() {
+                anyhow::bail!(
+                    "QOBUZ_RATE_LIMIT must be a number of requests per second, not '{requests_per_second}'"
+                );
+            }
+            if limit.requests_per_second > 0.0
+                && limit.requests_per_second < MIN_REQUESTS_PER_SECOND
+            {
+                anyhow::bail!(
+                    "QOBUZ_RATE_LIMIT must be 0 or at least {MIN_REQUESTS_PER_SECOND} requests per second, not '{requests_per_second}'"
+                );
+            }
+        }
+        if let Ok(burst) = std::env::var("QOBUZ_RATE_BURST") {
+            limit.burst = burst.parse::<u32>()?.max(1);
+        }
+
+        if limit.requests_per_second <= 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(limit))
+    }
+}
+
+/// A token bucket shared by every request an [`Api`](crate::api::Api)
+/// sends.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: Option<RateLimit>,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: Option<RateLimit>) -> Self {
+        let tokens = limit.map_or(0.0, |l| f64::from(l.burst));
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request can be sent.
+    pub(crate) async fn acquire(&self) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * limit.requests_per_second)
+                    .min(f64::from(limit.burst));
+                bucket.refilled_at = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / limit.requests_per_second)
+            };
+
+            tracing::debug!(?wait, "rate limited");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
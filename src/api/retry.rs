@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_mins(5);
+
+/// How requests that fail with a transient error are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The most times a request is sent, including the first attempt.
+    pub max_attempts: u32,
+    /// The delay before the first retry. Doubles with each attempt.
+    pub base_delay: Duration,
+    /// The longest delay between attempts, unless the server asks for more
+    /// with `Retry-After`.
+    pub max_delay: Duration,
+    /// The longest the server can ask us to wait with `Retry-After`. If it
+    /// asks for longer, the request fails instead.
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Reads the policy from `QOBUZ_RETRY_MAX_ATTEMPTS`,
+    /// `QOBUZ_RETRY_BASE_DELAY_MS`, `QOBUZ_RETRY_MAX_DELAY_MS` and
+    /// `QOBUZ_RETRY_MAX_RETRY_AFTER_MS`, using the defaults for anything that
+    /// isn't set.
+    /// # Errors
+    /// Will return `Err` if a variable isn't a number.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut policy = Self::default();
+        if let Ok(max_attempts) = std::env::var("QOBUZ_RETRY_MAX_ATTEMPTS") {
+            policy.max_attempts = max_attempts.parse::<u32>()?.max(1);
+        }
+        if let Ok(base_delay) = std::env::var("QOBUZ_RETRY_BASE_DELAY_MS") {
+            policy.base_delay = Duration::from_millis(base_delay.parse()?);
+        }
+        if let Ok(max_delay) = std::env::var("QOBUZ_RETRY_MAX_DELAY_MS") {
+            policy.max_delay = Duration::from_millis(max_delay.parse()?);
+        }
+        if let Ok(max_retry_after) = std::env::var("QOBUZ_RETRY_MAX_RETRY_AFTER_MS") {
+            policy.max_retry_after = Duration::from_millis(max_retry_after.parse()?);
+        }
+        Ok(policy)
+    }
+
+    /// The delay before retrying after the given (1-based) attempt. Uses
+    /// exponential backoff with "equal jitter": half the delay is fixed and
+    /// half is random, so concurrent clients don't retry in lockstep.
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(rand::rng().random::<f64>())
+    }
+}
+
+/// Whether a response status is worth retrying.
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// How long the server asked us to wait, from the `Retry-After` header.
+/// Supports both delay-seconds and HTTP-date values.
+pub(crate) fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or_default())
+}
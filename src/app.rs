@@ -1,35 +1,235 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    pin::pin,
+    time::Duration,
+};
 
 use anyhow::Context;
-use indicatif::ProgressIterator;
+use chrono::{DateTime, Local};
+use futures::{StreamExt, TryStreamExt, stream};
+use indicatif::{HumanBytes, ProgressBar};
 use itertools::Itertools;
 
 use crate::{
-    api::Api,
-    data::{self, artists, db::Db, playlists, releases, tracks},
+    api::{
+        Api, Catalog, ResponseCache,
+        models::{AlbumPage, ArtistPage, ArtistSummary, LabelPage},
+    },
+    data::{
+        self, artists, credentials, db::Db, http_cache, labels, pending, playlists, releases,
+        similar_artists, tracks,
+    },
+    types::{FollowList, FollowedArtist, FollowedLabel, QobuzLink, ReleaseClass, ReleaseType},
 };
 
-const DEFAULT_DB_NAME: &str = "music.db3";
+const RELEASE_LIST_PAGE_SIZE: usize = 50;
+const DEFAULT_NEW_RELEASE_MAX_AGE_DAYS: u32 = 90;
+const ARTIST_SEARCH_LIMIT: usize = 10;
+const FAVORITES_PAGE_SIZE: usize = 50;
+
+/// New releases found for an artist during a check.
+#[derive(Debug, Clone)]
+pub struct NewReleases {
+    pub artist: artists::Artist,
+    pub releases: Vec<releases::Release>,
+}
+
+/// New releases found for a label during a check.
+#[derive(Debug, Clone)]
+pub struct NewLabelReleases {
+    pub label: labels::Label,
+    pub releases: Vec<releases::Release>,
+}
+
+/// Who releases found during a check belong to.
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Artist(&'a artists::Artist),
+    Label(&'a labels::Label),
+}
 
-pub struct App {
+impl<'a> Source<'a> {
+    fn name(&self) -> &'a str {
+        match self {
+            Self::Artist(artist) => &artist.name,
+            Self::Label(label) => &label.name,
+        }
+    }
+
+    /// Stores releases and links them to the artist or label.
+    fn insert_releases(&self, db: &Db, releases: &[releases::Release]) -> anyhow::Result<()> {
+        match self {
+            Self::Artist(artist) => releases::insert_batch(db, artist.id, releases),
+            Self::Label(label) => releases::insert_batch_for_label(db, label.id, releases),
+        }
+    }
+}
+
+/// Options for checking for new releases.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// How many requests are made to the catalog at once.
+    pub concurrency: usize,
+    /// Releases that came out more than this many days before they're found
+    /// are back catalogue rather than new releases.
+    pub new_release_max_age_days: u32,
+    /// Classes of releases left out of the report. They're still stored, so
+    /// they aren't found again.
+    pub exclude: Vec<ReleaseClass>,
+    /// How releases that couldn't be loaded are retried.
+    pub pending: PendingRetry,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            new_release_max_age_days: DEFAULT_NEW_RELEASE_MAX_AGE_DAYS,
+            exclude: vec![],
+            pending: PendingRetry::default(),
+        }
+    }
+}
+
+/// How releases that 404'd or had no tracks when they were found are retried
+/// on later checks.
+#[derive(Debug, Clone)]
+pub struct PendingRetry {
+    /// How long to wait before the first retry. Doubles after each retry.
+    pub base_delay: Duration,
+    /// The longest wait between retries.
+    pub max_delay: Duration,
+    /// How long after the first failure to give up. Releases that are given
+    /// up on can still be retried by hand.
+    pub max_age: Duration,
+}
+
+impl Default for PendingRetry {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_hours(1),
+            max_delay: Duration::from_hours(24),
+            max_age: Duration::from_hours(24 * 30),
+        }
+    }
+}
+
+impl PendingRetry {
+    /// Reads the retry schedule from the environment, falling back to the
+    /// defaults.
+    /// # Errors
+    /// Will return `Err` if a variable is set but isn't a number.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut retry = Self::default();
+        if let Ok(base_delay) = std::env::var("QOBUZ_PENDING_BASE_DELAY_MINS") {
+            retry.base_delay = Duration::from_mins(base_delay.parse()?);
+        }
+        if let Ok(max_delay) = std::env::var("QOBUZ_PENDING_MAX_DELAY_HOURS") {
+            retry.max_delay = Duration::from_hours(max_delay.parse()?);
+        }
+        if let Ok(max_age) = std::env::var("QOBUZ_PENDING_MAX_AGE_DAYS") {
+            retry.max_age = Duration::from_hours(24 * max_age.parse::<u64>()?);
+        }
+        Ok(retry)
+    }
+
+    /// When a pending release should next be retried, or `None` if it's been
+    /// given up on.
+    #[must_use]
+    pub fn next_attempt_at(&self, pending: &pending::PendingRelease, now: i64) -> Option<i64> {
+        if pending.is_expired(now, self.max_age) {
+            return None;
+        }
+        Some(pending.next_attempt_at(self.base_delay, self.max_delay))
+    }
+}
+
+/// Options for generating a playlist.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistOptions {
+    /// Classes of releases left out of the playlist.
+    pub exclude: Vec<ReleaseClass>,
+}
+
+pub struct App<C = Api> {
     db: Db,
-    api: Api,
+    catalog: C,
 }
 
 impl App {
-    /// Initializes an App.
+    /// Initializes an App from the environment. The auth token comes from
+    /// `QOBUZ_AUTH_TOKEN` if it's set, otherwise from the credentials stored
+    /// by [`App::login`].
     /// # Errors
     /// Will return `Err` if there's an issue.
     pub fn init() -> anyhow::Result<Self> {
-        let db_path = std::env::var("QOBUZ_DB_PATH").unwrap_or(DEFAULT_DB_NAME.to_string());
-        let auth_token = std::env::var("QOBUZ_AUTH_TOKEN")?;
-        let app_id = std::env::var("QOBUZ_APP_ID")?;
-
+        let db_path = data::db::path_from_env();
         let db = Db::new(&db_path)?;
         data::db::init(&db)?;
 
-        let api = Api::new(&auth_token, &app_id)?;
-        Ok(Self { db, api })
+        let cache = match std::env::var("QOBUZ_CACHE") {
+            Ok(enabled) if !matches!(enabled.as_str(), "" | "0" | "false") => {
+                Some(ResponseCache::open(&db_path)?)
+            }
+            _ => None,
+        };
+
+        let api = Api::from_env_and_db(&db)?.with_cache(cache);
+        Self::new(db, api)
+    }
+
+    /// Logs in to Qobuz and stores the auth token, so it doesn't need to be
+    /// set in the environment.
+    /// # Errors
+    /// Will return `Err` if the credentials are wrong or there's an issue.
+    pub async fn login(&self, email: &str, password: &str) -> anyhow::Result<()> {
+        let login = self.catalog.login(email, password).await?;
+        credentials::set(
+            &self.db,
+            &credentials::Credentials {
+                user_id: login.user.id,
+                auth_token: login.user_auth_token,
+            },
+        )
+        .context("credentials::set")?;
+
+        println!("Logged in as user {}", login.user.id);
+        Ok(())
+    }
+
+    /// Forgets the stored auth token.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn logout(&self) -> anyhow::Result<()> {
+        if credentials::clear(&self.db)? {
+            println!("Logged out");
+        } else {
+            println!("Not logged in");
+        }
+        Ok(())
+    }
+}
+
+impl<C: Catalog> App<C> {
+    /// Creates an App from an existing database and catalog, making sure the
+    /// database schema is initialized.
+    /// # Errors
+    /// Will return `Err` if the database can't be initialized.
+    pub fn new(db: Db, catalog: C) -> anyhow::Result<Self> {
+        data::db::init(&db)?;
+        Ok(Self { db, catalog })
+    }
+
+    /// The database backing this App.
+    #[must_use]
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    /// The catalog this App checks for releases.
+    #[must_use]
+    pub fn catalog(&self) -> &C {
+        &self.catalog
     }
 
     /// Loads an artist into the database.
@@ -37,147 +237,890 @@ impl App {
     /// Will return `Err` if there's an issue.
     pub async fn load_artist(&self, artist_id: u32) -> anyhow::Result<()> {
         if let Some(name) = artists::get_by_id(&self.db, artist_id)? {
-            println!("Already loaded data for '{name}'");
+            if artists::is_followed(&self.db, artist_id)? {
+                println!("Already loaded data for '{name}'");
+            } else {
+                artists::insert(
+                    &self.db,
+                    &artists::Artist {
+                        id: artist_id,
+                        name: name.clone(),
+                    },
+                )?;
+                println!("Following '{name}' again");
+            }
             return Ok(());
         }
 
-        let artist_page = self.api.get_artist_page(artist_id).await?;
+        let artist_page = self.get_discography(artist_id).await?;
 
         println!("Loading data for '{}'", artist_page.name.display);
 
-        artists::insert(
-            &self.db,
-            &artists::Artist {
-                id: artist_page.id,
-                name: artist_page.name.display,
-            },
-        )?;
-
+        let similar = similar_to(&artist_page);
+        let now = chrono::Local::now();
         let rels = artist_page
             .releases
             .into_iter()
             .flat_map(|rels| {
-                rels.items
-                    .into_iter()
-                    .map(move |release| releases::Release {
+                rels.items.into_iter().map(move |release| {
+                    let details = releases::ReleaseDetails::from(&release);
+                    releases::Release {
                         id: release.id,
                         title: release.title,
                         release_type: rels.release_type,
-                    })
+                        class: None,
+                        upcoming: details.is_upcoming(now),
+                        details,
+                    }
+                })
             })
+            .unique_by(|release| release.id.clone())
             .collect::<Vec<_>>();
 
         let num_releases = rels.len();
-        releases::insert_batch(&self.db, artist_id, &rels)?;
+        self.db.transaction(|| {
+            artists::insert(
+                &self.db,
+                &artists::Artist {
+                    id: artist_page.id,
+                    name: artist_page.name.display,
+                },
+            )?;
+            releases::insert_batch(&self.db, artist_id, &rels)?;
+            similar_artists::replace(&self.db, artist_id, &similar)
+        })?;
         println!("Loaded {num_releases} releases");
 
         Ok(())
     }
 
-    /// Checks for new releases from artists in the database.
+    /// Searches the catalog for artists by name and lists them.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub async fn search_artists(&self, query: &str) -> anyhow::Result<()> {
+        let found = self
+            .catalog
+            .search_artists(query, ARTIST_SEARCH_LIMIT)
+            .await
+            .context("catalog.search_artists")?;
+        if found.is_empty() {
+            println!("No artists found");
+        }
+        for artist in found {
+            println!("{}", describe_artist(&artist));
+        }
+        Ok(())
+    }
+
+    /// Loads an artist found by name. If exactly one artist has that name,
+    /// or the search only finds one artist, they're loaded straight away.
+    /// Otherwise `choose` is given the candidates (the artists with that
+    /// name if there are several, or everything found) and returns the
+    /// index of the one to load.
+    /// # Errors
+    /// Will return `Err` if no artists are found, `choose` fails, or there's
+    /// an issue.
+    pub async fn load_artist_by_name(
+        &self,
+        name: &str,
+        choose: impl FnOnce(&[ArtistSummary]) -> anyhow::Result<usize>,
+    ) -> anyhow::Result<()> {
+        let name = name.trim();
+        let mut found = self
+            .catalog
+            .search_artists(name, ARTIST_SEARCH_LIMIT)
+            .await
+            .context("catalog.search_artists")?;
+        let same_name = found
+            .iter()
+            .filter(|artist| artist.name.to_lowercase() == name.to_lowercase())
+            .cloned()
+            .collect::<Vec<_>>();
+        if !same_name.is_empty() {
+            found = same_name;
+        }
+
+        let artist_id = match found.as_slice() {
+            [] => anyhow::bail!("no artists found for '{name}'"),
+            [artist] => artist.id,
+            candidates => {
+                let index = choose(candidates)?;
+                candidates
+                    .get(index)
+                    .with_context(|| format!("there's no artist {}", index + 1))?
+                    .id
+            }
+        };
+
+        self.load_artist(artist_id).await
+    }
+
+    /// Loads artists from a list with one artist id or Qobuz artist link per
+    /// line. Blank lines and anything after a `#` at the start of a line or
+    /// following whitespace are ignored. Entries that can't be loaded are
+    /// reported and skipped, and a summary is printed at the end.
+    /// # Errors
+    /// Will return `Err` if any entries couldn't be loaded, or there's an
+    /// issue with the database.
+    pub async fn load_artists(&self, list: &str) -> anyhow::Result<()> {
+        let (mut loaded, mut present, mut failed) = (0, 0, 0);
+        for entry in list.lines().map(strip_comment).filter(|e| !e.is_empty()) {
+            let artist_id = match QobuzLink::artist_id(entry) {
+                Ok(artist_id) => artist_id,
+                Err(e) => {
+                    eprintln!("Skipping: {e}");
+                    failed += 1;
+                    continue;
+                }
+            };
+            if artists::is_followed(&self.db, artist_id)? {
+                present += 1;
+                continue;
+            }
+            match self.load_artist(artist_id).await {
+                Ok(()) => loaded += 1,
+                Err(e) => {
+                    eprintln!("Couldn't load '{entry}': {e:#}");
+                    failed += 1;
+                }
+            }
+        }
+
+        let artist_msg = if loaded == 1 { "artist" } else { "artists" };
+        println!("Loaded {loaded} {artist_msg}, {present} already present, {failed} failed");
+        if failed > 0 {
+            anyhow::bail!("{failed} of the listed artists couldn't be loaded");
+        }
+        Ok(())
+    }
+
+    /// Loads the logged in user's favorite artists that aren't followed yet.
+    /// Artists that can't be loaded are reported and skipped. With `sync`,
+    /// followed artists who aren't favorites any more are unfollowed too,
+    /// keeping their releases if `keep_history` is set, unless some of the
+    /// favorites couldn't be loaded. The artists being unfollowed are listed
+    /// first.
+    /// # Errors
+    /// Will return `Err` if any favorites couldn't be loaded, if syncing with
+    /// no favorites at all would unfollow everyone, or there's an issue.
+    pub async fn import_favorites(&self, sync: bool, keep_history: bool) -> anyhow::Result<()> {
+        let mut favorites = vec![];
+        loop {
+            let page = self
+                .catalog
+                .get_favorite_artists(favorites.len(), FAVORITES_PAGE_SIZE)
+                .await
+                .context("catalog.get_favorite_artists")?;
+            let done = page.items.is_empty() || favorites.len() + page.items.len() >= page.total;
+            favorites.extend(page.items);
+            if done {
+                break;
+            }
+        }
+        let favorites = favorites
+            .into_iter()
+            .unique_by(|a| a.id)
+            .collect::<Vec<_>>();
+
+        let followed = artists::get_all(&self.db)?;
+        // Most likely a new or different account, rather than a reason to
+        // unfollow everyone.
+        if sync && favorites.is_empty() && !followed.is_empty() {
+            anyhow::bail!(
+                "no favorite artists found, refusing to unfollow all {} followed artists",
+                followed.len()
+            );
+        }
+        let followed_ids = followed.iter().map(|a| a.id).collect::<HashSet<_>>();
+        let (mut imported, mut failed) = (0, 0);
+        for favorite in &favorites {
+            if followed_ids.contains(&favorite.id) {
+                continue;
+            }
+            match self.load_artist(favorite.id).await {
+                Ok(()) => imported += 1,
+                Err(e) => {
+                    eprintln!("Couldn't load '{}': {e:#}", favorite.name);
+                    failed += 1;
+                }
+            }
+        }
+        let artist_msg = if imported == 1 { "artist" } else { "artists" };
+        println!(
+            "Imported {imported} favorite {artist_msg}, {} already followed, {failed} failed",
+            favorites.len() - imported - failed
+        );
+        if failed > 0 {
+            if sync {
+                anyhow::bail!(
+                    "{failed} of the favorite artists couldn't be loaded, so nobody was unfollowed"
+                );
+            }
+            anyhow::bail!("{failed} of the favorite artists couldn't be loaded");
+        }
+
+        if sync {
+            let favorite_ids = favorites.iter().map(|a| a.id).collect::<HashSet<_>>();
+            let unfollowed = followed
+                .into_iter()
+                .filter(|artist| !favorite_ids.contains(&artist.id))
+                .collect::<Vec<_>>();
+            if unfollowed.is_empty() {
+                return Ok(());
+            }
+            let artist_msg = if unfollowed.len() == 1 {
+                "artist"
+            } else {
+                "artists"
+            };
+            println!(
+                "Unfollowing {} {artist_msg} who aren't favorites any more:",
+                unfollowed.len()
+            );
+            for artist in &unfollowed {
+                println!("  • {} ({})", artist.name, artist.id);
+            }
+            for artist in unfollowed {
+                self.unfollow(artist.id, &artist.name, keep_history)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the followed artists and labels, to be imported into another
+    /// database with [`App::import_follows`].
     /// # Errors
     /// Will return `Err` if there's an issue.
-    pub async fn check_for_new_releases(&self) -> anyhow::Result<()> {
+    pub fn export_follows(&self) -> anyhow::Result<FollowList> {
+        let artists = artists::get_follows(&self.db)?
+            .into_iter()
+            .map(|follow| FollowedArtist {
+                id: follow.artist.id,
+                name: follow.artist.name,
+                followed_at: follow.followed_at,
+            })
+            .collect();
+        let labels = labels::get_follows(&self.db)?
+            .into_iter()
+            .map(|follow| FollowedLabel {
+                id: follow.label.id,
+                name: follow.label.name,
+                followed_at: Some(follow.followed_at),
+            })
+            .collect();
+        Ok(FollowList::new(artists, labels))
+    }
+
+    /// Follows the artists and labels in a list exported from another
+    /// database, loading the ones that aren't followed here yet and keeping
+    /// when they were followed. Artists and labels that are followed under a
+    /// different name here are reported as conflicts and left alone. Ones
+    /// that can't be loaded are reported and skipped.
+    /// # Errors
+    /// Will return `Err` if any artists or labels couldn't be loaded, or
+    /// there's an issue.
+    pub async fn import_follows(&self, list: &FollowList) -> anyhow::Result<()> {
+        let followed = artists::get_all(&self.db)?
+            .into_iter()
+            .map(|a| (a.id, a.name))
+            .collect::<HashMap<_, _>>();
+        let (mut imported, mut present, mut conflicts, mut failed) = (0, 0, 0, 0);
+        for artist in list.artists.iter().unique_by(|a| a.id) {
+            match followed.get(&artist.id) {
+                Some(name) if *name == artist.name => present += 1,
+                Some(name) => {
+                    println!(
+                        "Conflict: artist {} is '{name}' here but '{}' in the import, keeping '{name}'",
+                        artist.id, artist.name
+                    );
+                    conflicts += 1;
+                }
+                None => {
+                    if let Err(e) = self.load_artist(artist.id).await {
+                        eprintln!(
+                            "Couldn't load artist {} '{}': {e:#}",
+                            artist.id, artist.name
+                        );
+                        failed += 1;
+                        continue;
+                    }
+                    if let Some(followed_at) = &artist.followed_at {
+                        artists::set_followed_at(&self.db, artist.id, followed_at)?;
+                    }
+                    imported += 1;
+                }
+            }
+        }
+
+        let artist_msg = if imported == 1 { "artist" } else { "artists" };
+        let conflict_msg = if conflicts == 1 {
+            "conflict"
+        } else {
+            "conflicts"
+        };
+        println!(
+            "Imported {imported} {artist_msg}, {present} already followed, {conflicts} {conflict_msg}, {failed} failed"
+        );
+        let mut all_failed = failed;
+
+        if !list.labels.is_empty() {
+            let (mut imported, mut present, mut conflicts, mut failed) = (0, 0, 0, 0);
+            for label in list.labels.iter().unique_by(|l| l.id) {
+                match labels::get_by_id(&self.db, label.id)? {
+                    Some(name) if name == label.name => present += 1,
+                    Some(name) => {
+                        println!(
+                            "Conflict: label {} is '{name}' here but '{}' in the import, keeping '{name}'",
+                            label.id, label.name
+                        );
+                        conflicts += 1;
+                    }
+                    None => {
+                        if let Err(e) = self.follow_label(label.id).await {
+                            eprintln!("Couldn't load label {} '{}': {e:#}", label.id, label.name);
+                            failed += 1;
+                            continue;
+                        }
+                        if let Some(followed_at) = &label.followed_at {
+                            labels::set_followed_at(&self.db, label.id, followed_at)?;
+                        }
+                        imported += 1;
+                    }
+                }
+            }
+
+            let label_msg = if imported == 1 { "label" } else { "labels" };
+            let conflict_msg = if conflicts == 1 {
+                "conflict"
+            } else {
+                "conflicts"
+            };
+            println!(
+                "Imported {imported} {label_msg}, {present} already followed, {conflicts} {conflict_msg}, {failed} failed"
+            );
+            all_failed += failed;
+        }
+
+        if all_failed > 0 {
+            anyhow::bail!("{all_failed} of the imported artists and labels couldn't be loaded");
+        }
+        Ok(())
+    }
+
+    /// Stops following an artist, given by id, link or name. Their releases and
+    /// tracks are deleted unless another artist shares them, or
+    /// `keep_history` is set, in which case everything is kept and the
+    /// artist just isn't checked any more. An artist who was unfollowed
+    /// with `keep_history` can be unfollowed again without it, to delete
+    /// what was kept.
+    /// # Errors
+    /// Will return `Err` if the artist isn't in the database, is already
+    /// unfollowed and `keep_history` is set, or there's an issue.
+    pub fn unfollow_artist(&self, artist: &str, keep_history: bool) -> anyhow::Result<()> {
+        let artist_id = match QobuzLink::artist_id(artist) {
+            Ok(artist_id) => Some(artist_id),
+            Err(_) => artists::get_id_by_name(&self.db, artist)?,
+        };
+        let name = match artist_id {
+            Some(artist_id) => artists::get_by_id(&self.db, artist_id)?,
+            None => None,
+        };
+        let (Some(artist_id), Some(name)) = (artist_id, name) else {
+            anyhow::bail!("'{artist}' isn't a followed artist");
+        };
+        if keep_history && !artists::is_followed(&self.db, artist_id)? {
+            anyhow::bail!("'{name}' is already unfollowed");
+        }
+
+        self.unfollow(artist_id, &name, keep_history)
+    }
+
+    /// Stops following an artist who's in the database, followed or not.
+    fn unfollow(&self, artist_id: u32, name: &str, keep_history: bool) -> anyhow::Result<()> {
+        if keep_history {
+            artists::unfollow(&self.db, artist_id)?;
+            println!("Unfollowed '{name}', keeping their releases");
+            return Ok(());
+        }
+
+        let (num_releases, num_tracks) = self.db.transaction(|| {
+            artists::delete(&self.db, artist_id)?;
+            let num_releases = releases::delete_orphans(&self.db)?;
+            let num_tracks = tracks::delete_orphans(&self.db)?;
+            Ok((num_releases, num_tracks))
+        })?;
+        println!("Unfollowed '{name}', removing {num_releases} releases and {num_tracks} tracks");
+        Ok(())
+    }
+
+    /// Gets an artist's page with all of their releases. The artist page only
+    /// includes the first few releases of each type, so the rest are paged
+    /// in from the release list.
+    async fn get_discography(&self, artist_id: u32) -> anyhow::Result<ArtistPage> {
+        let mut artist_page = self.catalog.get_artist_page(artist_id).await?;
+
+        for rels in artist_page.releases.iter_mut().filter(|rels| rels.has_more) {
+            loop {
+                let release_list = self
+                    .catalog
+                    .get_artist_releases(
+                        artist_id,
+                        rels.release_type,
+                        rels.items.len(),
+                        RELEASE_LIST_PAGE_SIZE,
+                    )
+                    .await
+                    .context("catalog.get_artist_releases")?;
+
+                let done = !release_list.has_more || release_list.items.is_empty();
+                rels.items.extend(release_list.items);
+                if done {
+                    break;
+                }
+            }
+            rels.has_more = false;
+        }
+
+        Ok(artist_page)
+    }
+
+    /// Checks for new releases from artists in the database. Returns the
+    /// releases that were verified and loaded, grouped by artist in the order
+    /// the artists were checked.
+    ///
+    /// Upcoming releases are checked again every time, and are loaded like
+    /// new releases once they can be streamed. Releases that couldn't be
+    /// loaded are retried according to `options.pending`. Releases already
+    /// stored for another artist or a label are linked to the artist without
+    /// being reported.
+    ///
+    /// Artist pages and release tracks are fetched up to
+    /// `options.concurrency` at a time, while database writes happen one
+    /// artist at a time.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub async fn check_for_new_releases(
+        &self,
+        options: &CheckOptions,
+    ) -> anyhow::Result<Vec<NewReleases>> {
+        let concurrency = options.concurrency.max(1);
         let all_artists = artists::get_all(&self.db)?;
         println!("Checking {} artists\n", all_artists.len());
-        let mut all_new_releases = HashMap::new();
-        for artist in all_artists.iter().progress() {
-            let existing_release_ids = releases::get_all_for_artist(&self.db, artist.id)?
-                .into_iter()
-                .map(|r| r.id)
+
+        let now = chrono::Local::now().timestamp();
+        let pending_releases = pending::get_all(&self.db)?;
+        let is_due = |release: &releases::Release| {
+            pending_releases.get(&release.id).is_some_and(|pending| {
+                options
+                    .pending
+                    .next_attempt_at(pending, now)
+                    .is_some_and(|at| at <= now)
+            })
+        };
+
+        // Releases shared by several artists are only retried for the first.
+        let mut existing_release_ids = HashMap::new();
+        let mut retried_releases = HashMap::new();
+        let mut retried_ids = HashSet::new();
+        for artist in &all_artists {
+            let artist_releases = releases::get_all_for_artist(&self.db, artist.id)?;
+            let release_ids = artist_releases
+                .iter()
+                .map(|r| r.id.clone())
                 .collect::<HashSet<_>>();
+            let retried = artist_releases
+                .into_iter()
+                .filter(|r| (r.upcoming || is_due(r)) && retried_ids.insert(r.id.clone()))
+                .collect::<Vec<_>>();
+            existing_release_ids.insert(artist.id, release_ids);
+            retried_releases.insert(artist.id, retried);
+        }
+
+        let progress = ProgressBar::new(all_artists.len() as u64);
+        let artist_pages = stream::iter(&all_artists)
+            .map(|artist| self.get_discography(artist.id))
+            .buffered(concurrency)
+            .inspect(|_| progress.inc(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        progress.finish();
 
-            let api_releases = self
-                .api
-                .get_artist_page(artist.id)
-                .await?
+        // Releases already stored for someone else (another artist or a
+        // label) are linked to the artist without being reported again.
+        let stored_release_ids = releases::get_all_ids(&self.db)?;
+        let mut all_similar = vec![];
+        let mut all_known = vec![];
+        let mut all_new_releases = vec![];
+        for (artist, artist_page) in all_artists.iter().zip(artist_pages) {
+            all_similar.push((artist.id, similar_to(&artist_page)));
+            let existing_release_ids = &existing_release_ids[&artist.id];
+            let mut seen = HashSet::new();
+            let new_releases = artist_page
                 .releases
                 .into_iter()
                 .flat_map(|rels| rels.items.into_iter().map(move |r| (rels.release_type, r)))
-                .map(|(r_type, r)| (r.id.clone(), (r_type, r)))
-                .collect::<HashMap<_, _>>();
-
-            let new_releases = api_releases
+                .filter(|(_, r)| !existing_release_ids.contains(&r.id))
+                .filter(|(_, r)| seen.insert(r.id.clone()))
+                .map(|(release_type, release)| releases::Release {
+                    details: releases::ReleaseDetails::from(&release),
+                    id: release.id,
+                    title: release.title,
+                    release_type,
+                    class: None,
+                    upcoming: false,
+                });
+            let (known, new_releases): (Vec<_>, Vec<_>) =
+                new_releases.partition(|r| stored_release_ids.contains(&r.id));
+            all_known.push((artist.id, known));
+            let new_releases = new_releases
                 .into_iter()
-                .filter(|(release_id, _)| !existing_release_ids.contains(release_id))
-                .map(|(_, release)| release)
+                .chain(retried_releases.remove(&artist.id).unwrap_or_default())
                 .collect::<Vec<_>>();
 
             if !new_releases.is_empty() {
-                let rels = new_releases
-                    .into_iter()
-                    .map(|(release_type, release)| releases::Release {
-                        id: release.id,
-                        title: release.title,
-                        release_type,
-                    })
-                    .collect::<Vec<_>>();
-
-                all_new_releases.insert(artist, rels);
+                all_new_releases.push((Source::Artist(artist), new_releases));
             }
         }
 
+        self.db.transaction(|| {
+            for (artist_id, similar) in &all_similar {
+                similar_artists::replace(&self.db, *artist_id, similar)?;
+            }
+            for (artist_id, known) in &all_known {
+                releases::link_to_artist(&self.db, *artist_id, known)?;
+            }
+            Ok(())
+        })?;
+
         if all_new_releases.is_empty() {
             println!("No new music found");
+            return Ok(vec![]);
+        }
+
+        let found = self.load_releases(all_new_releases, options).await?;
+        if found.is_empty() {
+            println!("No new music found");
+        }
+
+        Ok(artist_releases(found))
+    }
+
+    /// Starts following a label, loading all of its releases so only ones
+    /// that come out later are found by checks.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub async fn follow_label(&self, label_id: u64) -> anyhow::Result<()> {
+        if let Some(name) = labels::get_by_id(&self.db, label_id)? {
+            println!("Already following label '{name}'");
             return Ok(());
         }
 
-        for (artist, new_releases) in all_new_releases {
+        let label_page = self.get_label_catalog(label_id).await?;
+        println!("Loading data for label '{}'", label_page.name);
+
+        let now = chrono::Local::now();
+        let rels = label_releases(label_page.albums.items, now);
+        let num_releases = rels.len();
+        self.db.transaction(|| {
+            labels::insert(
+                &self.db,
+                &labels::Label {
+                    id: label_page.id,
+                    name: label_page.name,
+                },
+            )?;
+            releases::insert_batch_for_label(&self.db, label_id, &rels)
+        })?;
+        println!("Loaded {num_releases} releases");
+
+        Ok(())
+    }
+
+    /// Gets a label with all of its releases, paging through them.
+    async fn get_label_catalog(&self, label_id: u64) -> anyhow::Result<LabelPage> {
+        let mut label_page: Option<LabelPage> = None;
+        loop {
+            let offset = label_page.as_ref().map_or(0, |l| l.albums.items.len());
+            let page = self
+                .catalog
+                .get_label(label_id, offset, RELEASE_LIST_PAGE_SIZE)
+                .await
+                .context("catalog.get_label")?;
+            let done = page.albums.items.is_empty();
+            let label = match label_page.take() {
+                Some(mut label) => {
+                    label.albums.items.extend(page.albums.items);
+                    label
+                }
+                None => page,
+            };
+
+            if done || label.albums.items.len() >= label.albums.total {
+                return Ok(label);
+            }
+            label_page = Some(label);
+        }
+    }
+
+    /// Checks for new releases from followed labels, like
+    /// [`App::check_for_new_releases`] does for artists. Releases that are
+    /// already stored, e.g. because a followed artist put them out, are
+    /// linked to the label without being reported again.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub async fn check_labels(
+        &self,
+        options: &CheckOptions,
+    ) -> anyhow::Result<Vec<NewLabelReleases>> {
+        let concurrency = options.concurrency.max(1);
+        let all_labels = labels::get_all(&self.db)?;
+        if all_labels.is_empty() {
+            return Ok(vec![]);
+        }
+        println!("\nChecking {} labels\n", all_labels.len());
+
+        let now = chrono::Local::now();
+        let pending_releases = pending::get_all(&self.db)?;
+        let is_due = |release: &releases::Release| {
+            pending_releases.get(&release.id).is_some_and(|pending| {
+                options
+                    .pending
+                    .next_attempt_at(pending, now.timestamp())
+                    .is_some_and(|at| at <= now.timestamp())
+            })
+        };
+
+        let progress = ProgressBar::new(all_labels.len() as u64);
+        let label_pages = stream::iter(&all_labels)
+            .map(|label| self.get_label_catalog(label.id))
+            .buffered(concurrency)
+            .inspect(|_| progress.inc(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        progress.finish();
+
+        let stored_release_ids = releases::get_all_ids(&self.db)?;
+        let mut all_new_releases = vec![];
+        for (label, label_page) in all_labels.iter().zip(label_pages) {
+            let label_releases_stored = releases::get_all_for_label(&self.db, label.id)?;
+            let linked_ids = label_releases_stored
+                .iter()
+                .map(|r| r.id.clone())
+                .collect::<HashSet<_>>();
+            let (known, new): (Vec<_>, Vec<_>) = label_releases(label_page.albums.items, now)
+                .into_iter()
+                .filter(|r| !linked_ids.contains(&r.id))
+                .partition(|r| stored_release_ids.contains(&r.id));
+            releases::link_to_label(&self.db, label.id, known.iter().map(|r| r.id.as_str()))?;
+
+            let new_releases = new
+                .into_iter()
+                .map(|release| releases::Release {
+                    upcoming: false,
+                    ..release
+                })
+                .chain(
+                    label_releases_stored
+                        .into_iter()
+                        .filter(|r| r.upcoming || is_due(r)),
+                )
+                .collect::<Vec<_>>();
+            if !new_releases.is_empty() {
+                all_new_releases.push((Source::Label(label), new_releases));
+            }
+        }
+
+        if all_new_releases.is_empty() {
+            println!("No new label releases found");
+            return Ok(vec![]);
+        }
+
+        let found = self.load_releases(all_new_releases, options).await?;
+        if found.is_empty() {
+            println!("No new label releases found");
+        }
+
+        Ok(found
+            .into_iter()
+            .filter_map(|(source, releases)| match source {
+                Source::Label(label) => Some(NewLabelReleases {
+                    label: label.clone(),
+                    releases,
+                }),
+                Source::Artist(_) => None,
+            })
+            .collect())
+    }
+
+    /// Fetches releases from the catalog and stores the ones that can be
+    /// loaded, returning them grouped by artist or label. Each artist's or
+    /// label's releases are stored as soon as they've all been fetched, so a
+    /// failure later on doesn't undo the ones before it.
+    async fn load_releases<'a>(
+        &self,
+        all_new_releases: Vec<(Source<'a>, Vec<releases::Release>)>,
+        options: &CheckOptions,
+    ) -> anyhow::Result<Vec<(Source<'a>, Vec<releases::Release>)>> {
+        let concurrency = options.concurrency.max(1);
+        let release_ids = all_new_releases
+            .iter()
+            .flat_map(|(_, new_releases)| new_releases.iter().map(|r| r.id.clone()))
+            .collect::<Vec<_>>();
+        let mut all_albums = pin!(
+            stream::iter(release_ids)
+                .map(|release_id| async move {
+                    self.catalog
+                        .get_release(&release_id)
+                        .await
+                        .context("catalog.get_release")
+                })
+                .buffered(concurrency)
+        );
+
+        // A release found by several artists only counts as one attempt.
+        let mut attempted = HashSet::new();
+        let mut found = vec![];
+        for (source, new_releases) in all_new_releases {
+            let albums = all_albums
+                .by_ref()
+                .take(new_releases.len())
+                .try_collect()
+                .await?;
+            if let Some(new_releases) =
+                self.store_new_releases(source, new_releases, albums, &mut attempted, options)?
+            {
+                found.push((source, new_releases));
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Stores an artist's new releases, verifying the ones that have tracks
+    /// and can be streamed, and lets the user know about the ones that aren't
+    /// excluded. `albums` holds what the catalog returned for each release,
+    /// if anything. Failed releases already in `attempted` aren't counted
+    /// again.
+    fn store_new_releases(
+        &self,
+        source: Source,
+        mut new_releases: Vec<releases::Release>,
+        albums: Vec<Option<AlbumPage>>,
+        attempted: &mut HashSet<String>,
+        options: &CheckOptions,
+    ) -> anyhow::Result<Option<Vec<releases::Release>>> {
+        // The release itself knows more than the artist page, like when it
+        // actually came out.
+        let now = chrono::Local::now();
+        let mut announced = vec![];
+        for (release, album) in new_releases.iter_mut().zip(&albums) {
+            if let Some(album) = album {
+                release.details =
+                    releases::ReleaseDetails::from(album).or(std::mem::take(&mut release.details));
+            }
+            release.class = Some(ReleaseClass::classify(
+                release.details.release_date.as_deref(),
+                now.date_naive(),
+                options.new_release_max_age_days,
+            ));
+
+            let upcoming = release.details.is_upcoming(now);
+            if upcoming && !release.upcoming {
+                announced.push(release.clone());
+            }
+            release.upcoming = upcoming;
+        }
+
+        // Everything for the artist or label is written at once, so a failure
+        // part way through doesn't leave releases that are never looked at
+        // again.
+        let mut loaded_releases = self.db.transaction(|| {
             // Not all found releases are real. We need to wait until we
             // confirm the release tracks can be loaded. Sometimes releases
-            // 404 or don't have tracks.
-            releases::insert_batch(&self.db, artist.id, &new_releases)
+            // 404, don't have tracks or still can't be streamed once their
+            // date has passed.
+            source
+                .insert_releases(&self.db, &new_releases)
                 .context("releases::insert_batch")?;
 
             let mut loaded_releases = vec![];
-            for release in new_releases {
-                let tracks = self
-                    .api
-                    .get_release_tracks(&release.id)
-                    .await
-                    .context("api.get_release_tracks")?;
-
-                if tracks.is_empty() {
+            let mut failed_release_ids = vec![];
+            for (release, album) in new_releases.into_iter().zip(albums) {
+                if release.upcoming {
                     continue;
                 }
+                let Some(album) =
+                    album.filter(|a| a.streamable != Some(false) && !a.tracks.items.is_empty())
+                else {
+                    failed_release_ids.push(release.id);
+                    continue;
+                };
 
-                loaded_releases.push(release.clone());
-                tracks::insert_batch(&self.db, &release.id, tracks)
+                tracks::insert_batch(&self.db, &release.id, album.tracks.items)
                     .context("tracks::insert_batch")?;
+                loaded_releases.push(release);
             }
 
-            // All the releases were bogus. Go to the next artist.
-            if loaded_releases.is_empty() {
-                continue;
-            }
+            // Try the failed ones again on a later check.
+            failed_release_ids.retain(|id| attempted.insert(id.clone()));
+            pending::record_attempt(&self.db, &failed_release_ids, now.timestamp())
+                .context("pending::record_attempt")?;
 
             // Finalize the loaded releases.
-            releases::bulk_verify(
-                &self.db,
-                &loaded_releases
-                    .iter()
-                    .map(|r| r.id.clone())
-                    .collect::<Vec<_>>(),
-            )
-            .context("releases::bulk_verify")?;
-
-            // Let the user know what happened.
-            let num_releases = loaded_releases.len();
-            let release_msg = if num_releases == 1 {
-                "release"
-            } else {
-                "releases"
+            let loaded_release_ids = loaded_releases
+                .iter()
+                .map(|r| r.id.clone())
+                .collect::<Vec<_>>();
+            releases::bulk_verify(&self.db, &loaded_release_ids)
+                .context("releases::bulk_verify")?;
+            pending::remove(&self.db, &loaded_release_ids).context("pending::remove")?;
+
+            Ok(loaded_releases)
+        })?;
+
+        print_announced(source.name(), &announced);
+
+        // Let the user know what happened.
+        loaded_releases.retain(|r| {
+            r.class
+                .is_none_or(|class| !options.exclude.contains(&class))
+        });
+        if loaded_releases.is_empty() {
+            return Ok(None);
+        }
+
+        for class in [ReleaseClass::NewRelease, ReleaseClass::BackCatalogue] {
+            let releases = loaded_releases
+                .iter()
+                .filter(|r| r.class == Some(class))
+                .collect::<Vec<_>>();
+            let release_msg = match (class, releases.len()) {
+                (_, 0) => continue,
+                (ReleaseClass::NewRelease, 1) => "new release",
+                (ReleaseClass::NewRelease, _) => "new releases",
+                (ReleaseClass::BackCatalogue, 1) => "back catalogue release",
+                (ReleaseClass::BackCatalogue, _) => "back catalogue releases",
             };
-            println!("Found {num_releases} new {release_msg} for {}", artist.name);
-            let release_log = loaded_releases
+            println!(
+                "Found {} {release_msg} for {}",
+                releases.len(),
+                source.name()
+            );
+            let release_log = releases
                 .iter()
-                .map(|r| format!("  • {}", r.title))
+                .map(|r| match (class, &r.details.release_date) {
+                    (ReleaseClass::BackCatalogue, Some(release_date)) => {
+                        format!("  • {} ({release_date})", r.title)
+                    }
+                    _ => format!("  • {}", r.title),
+                })
                 .collect::<Vec<_>>()
                 .join("\n");
             println!("{release_log}");
         }
 
-        Ok(())
+        Ok(Some(loaded_releases))
     }
 
     /// List artists in the database.
@@ -195,8 +1138,46 @@ impl App {
         Ok(())
     }
 
-    pub fn list_releases_for_artist(&self, artist: String) -> anyhow::Result<()> {
-        if let Some(artist_id) = artists::get_id_by_name(&self.db, &artist)? {
+    /// Lists up to `limit` artists who aren't followed, ranked by how many
+    /// followed artists Qobuz lists them as similar to. Similar artists are
+    /// picked up when artists are loaded or checked.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn list_recommendations(&self, limit: usize) -> anyhow::Result<()> {
+        let recommendations = similar_artists::get_recommendations(&self.db)?;
+        if recommendations.is_empty() {
+            println!("No recommendations yet, check for new releases to find some");
+        }
+        for (rank, recommendation) in recommendations.iter().take(limit).enumerate() {
+            println!(
+                "{}. {} ({}) · similar to {}",
+                rank + 1,
+                recommendation.artist.name,
+                recommendation.artist.id,
+                recommendation.similar_to.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Loads the artist at `rank` (starting from 1) in the recommendations.
+    /// # Errors
+    /// Will return `Err` if there's no recommendation at that rank, or
+    /// there's an issue.
+    pub async fn load_recommendation(&self, rank: usize) -> anyhow::Result<()> {
+        let recommendations = similar_artists::get_recommendations(&self.db)?;
+        let recommendation = rank
+            .checked_sub(1)
+            .and_then(|index| recommendations.get(index))
+            .with_context(|| format!("there's no recommendation {rank}"))?;
+        self.load_artist(recommendation.artist.id).await
+    }
+
+    /// List all the releases in the database for an artist, grouped by type.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn list_releases_for_artist(&self, artist: &str) -> anyhow::Result<()> {
+        if let Some(artist_id) = artists::get_id_by_name(&self.db, artist)? {
             println!("Releases for {artist}");
             let mut releases_by_type = releases::get_all_for_artist(&self.db, artist_id)?
                 .into_iter()
@@ -206,43 +1187,408 @@ impl App {
 
             releases_by_type.sort_by_key(|r| r.0);
 
-            for (release_type, releases) in releases_by_type {
+            for (release_type, mut releases) in releases_by_type {
                 println!("  {release_type:?}");
+                releases.sort_by(|a, b| a.details.release_date.cmp(&b.details.release_date));
                 for release in releases {
-                    println!("    - {}", release.title);
+                    println!("    - {}", describe_release(&release));
                 }
             }
         } else {
-            println!("Couldn't find an artist by that name.")
+            println!("Couldn't find an artist by that name.");
         }
         Ok(())
     }
 
+    /// List releases that have been announced but can't be streamed yet,
+    /// soonest first.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn list_upcoming(&self) -> anyhow::Result<()> {
+        let (all_artists, all_labels) = (artists::get_all(&self.db)?, labels::get_all(&self.db)?);
+        let mut upcoming = vec![];
+        for (source, source_releases) in self.followed_releases(&all_artists, &all_labels)? {
+            for release in source_releases {
+                if release.upcoming {
+                    upcoming.push((release.details.stream_date(), source.name(), release));
+                }
+            }
+        }
+
+        if upcoming.is_empty() {
+            println!("No upcoming releases");
+            return Ok(());
+        }
+
+        upcoming.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        for (stream_date, artist, release) in upcoming {
+            let stream_date = stream_date.as_deref().unwrap_or("date unknown");
+            println!("{stream_date}  {artist} - {}", release.title);
+        }
+        Ok(())
+    }
+
+    /// List releases that couldn't be loaded when they were found, and when
+    /// they'll next be retried.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn list_pending(&self, retry: &PendingRetry) -> anyhow::Result<()> {
+        let pending_releases = pending::get_all(&self.db)?;
+        let (all_artists, all_labels) = (artists::get_all(&self.db)?, labels::get_all(&self.db)?);
+        let mut listed = vec![];
+        for (source, source_releases) in self.followed_releases(&all_artists, &all_labels)? {
+            for release in source_releases {
+                if let Some(pending) = pending_releases.get(&release.id) {
+                    listed.push((pending, source.name(), release));
+                }
+            }
+        }
+
+        if listed.is_empty() {
+            println!("No pending releases");
+            return Ok(());
+        }
+
+        let now = chrono::Local::now().timestamp();
+        listed.sort_by_key(|(pending, _, _)| (pending.first_attempt_at, &pending.release_id));
+        for (pending, artist, release) in listed {
+            let next_attempt = match retry.next_attempt_at(pending, now) {
+                Some(at) if at <= now => "retrying on the next check".to_string(),
+                Some(at) => chrono::DateTime::from_timestamp(at, 0).map_or_else(
+                    || "retrying later".to_string(),
+                    |at| {
+                        let at = at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+                        format!("retrying after {at}")
+                    },
+                ),
+                None => "given up".to_string(),
+            };
+            let attempts_msg = if pending.attempts == 1 {
+                "attempt"
+            } else {
+                "attempts"
+            };
+            println!(
+                "{}  {artist} - {} ({} failed {attempts_msg}, {next_attempt})",
+                release.id, release.title, pending.attempts
+            );
+        }
+        Ok(())
+    }
+
+    /// Retries loading pending releases straight away, or all of them if no
+    /// release ids are given. Returns the releases that were loaded.
+    /// # Errors
+    /// Will return `Err` if a release isn't pending or there's an issue.
+    pub async fn retry_pending(
+        &self,
+        release_ids: &[String],
+        options: &CheckOptions,
+    ) -> anyhow::Result<Vec<NewReleases>> {
+        let pending_releases = pending::get_all(&self.db)?;
+        if let Some(release_id) = release_ids
+            .iter()
+            .find(|id| !pending_releases.contains_key(*id))
+        {
+            anyhow::bail!("release {release_id} isn't pending");
+        }
+
+        let (all_artists, all_labels) = (artists::get_all(&self.db)?, labels::get_all(&self.db)?);
+        let mut retried_releases = vec![];
+        for (source, source_releases) in self.followed_releases(&all_artists, &all_labels)? {
+            let retried = source_releases
+                .into_iter()
+                .filter(|r| pending_releases.contains_key(&r.id))
+                .filter(|r| release_ids.is_empty() || release_ids.contains(&r.id))
+                .collect::<Vec<_>>();
+            if !retried.is_empty() {
+                retried_releases.push((source, retried));
+            }
+        }
+
+        if retried_releases.is_empty() {
+            println!("No pending releases");
+            return Ok(vec![]);
+        }
+
+        let found = self.load_releases(retried_releases, options).await?;
+        if found.is_empty() {
+            println!("Couldn't load any pending releases");
+        }
+        Ok(artist_releases(found))
+    }
+
+    /// The stored releases of every followed artist and label. Releases
+    /// shared by several of them are only included for the first.
+    fn followed_releases<'a>(
+        &self,
+        all_artists: &'a [artists::Artist],
+        all_labels: &'a [labels::Label],
+    ) -> anyhow::Result<Vec<(Source<'a>, Vec<releases::Release>)>> {
+        let mut seen = HashSet::new();
+        let mut followed = vec![];
+        for artist in all_artists {
+            let mut artist_releases = releases::get_all_for_artist(&self.db, artist.id)?;
+            artist_releases.retain(|r| seen.insert(r.id.clone()));
+            followed.push((Source::Artist(artist), artist_releases));
+        }
+        for label in all_labels {
+            let mut label_releases = releases::get_all_for_label(&self.db, label.id)?;
+            label_releases.retain(|r| seen.insert(r.id.clone()));
+            followed.push((Source::Label(label), label_releases));
+        }
+        Ok(followed)
+    }
+
+    /// Stops retrying pending releases.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn forget_pending(&self, release_ids: &[String]) -> anyhow::Result<()> {
+        let forgotten = pending::remove(&self.db, release_ids)?;
+        let release_msg = if forgotten == 1 {
+            "release"
+        } else {
+            "releases"
+        };
+        println!("Forgot {forgotten} pending {release_msg}");
+        Ok(())
+    }
+
+    /// Show how many API responses are cached for each endpoint.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn cache_stats(&self) -> anyhow::Result<()> {
+        let stats = http_cache::stats(&self.db)?;
+        if stats.is_empty() {
+            println!("The cache is empty");
+            return Ok(());
+        }
+
+        let (mut entries, mut bytes) = (0, 0);
+        for endpoint in stats {
+            println!(
+                "{}: {} responses ({})",
+                endpoint.endpoint,
+                endpoint.entries,
+                HumanBytes(endpoint.bytes)
+            );
+            entries += endpoint.entries;
+            bytes += endpoint.bytes;
+        }
+        println!("Total: {entries} responses ({})", HumanBytes(bytes));
+        Ok(())
+    }
+
+    /// Remove all cached API responses.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn clear_cache(&self) -> anyhow::Result<()> {
+        let cleared = http_cache::clear(&self.db)?;
+        println!("Cleared {cleared} cached responses");
+        Ok(())
+    }
+
     /// Generate a playlist for latest releases that haven't been put into a
-    /// playlist.
+    /// playlist. Returns the created playlist, if any.
     /// # Errors
     /// Will return `Err` if there's an issue.
-    pub async fn gen_playlist(&self) -> anyhow::Result<()> {
+    pub async fn gen_playlist(
+        &self,
+        options: &PlaylistOptions,
+    ) -> anyhow::Result<Option<playlists::Playlist>> {
         let name = chrono::Local::now().date_naive().to_string();
-        let track_ids = tracks::get_latest(&self.db)?;
+        let track_ids = tracks::get_latest(&self.db, &options.exclude)?;
 
         if track_ids.is_empty() {
             println!("No new tracks. Skipping playlist creation");
-            return Ok(());
+            return Ok(None);
         }
 
-        let id = self.api.create_playlist(&name, track_ids).await?;
-        playlists::insert(
-            &self.db,
-            &playlists::Playlist {
-                id,
-                name: name.clone(),
-            },
-        )
-        .context("playlists::insert")?;
+        let id = self.catalog.create_playlist(&name, track_ids).await?;
+        let playlist = playlists::Playlist { id, name };
+        playlists::insert(&self.db, &playlist).context("playlists::insert")?;
 
-        println!("Created playlist: {name}");
+        println!("Created playlist: {}", playlist.name);
 
-        Ok(())
+        Ok(Some(playlist))
+    }
+}
+
+/// Show the database's schema version and any migrations it's missing.
+/// # Errors
+/// Will return `Err` if there's an issue.
+pub fn db_status(db: &Db) -> anyhow::Result<()> {
+    let version = data::db::schema_version(db)?;
+    let latest = data::db::latest_version();
+    let pending = data::db::pending_migrations(db)?;
+    if pending.is_empty() {
+        println!("Schema version {version} (up to date)");
+        return Ok(());
+    }
+
+    println!("Schema version {version} of {latest}");
+    println!("Pending migrations:");
+    for migration in pending {
+        println!("  {}: {}", migration.version, migration.name);
+    }
+    Ok(())
+}
+
+/// Apply any migrations the database is missing.
+/// # Errors
+/// Will return `Err` if a migration fails.
+pub fn db_migrate(db: &Db) -> anyhow::Result<()> {
+    let applied = data::db::migrate(db)?;
+    for migration in &applied {
+        println!(
+            "Applied migration {}: {}",
+            migration.version, migration.name
+        );
+    }
+    let version = data::db::schema_version(db)?;
+    if applied.is_empty() {
+        println!("Schema version {version} is already up to date");
+    } else {
+        println!("Schema version {version} is up to date");
+    }
+    Ok(())
+}
+
+/// Turns a label's releases into releases to be stored. Labels don't say
+/// what type their releases are.
+fn label_releases(albums: Vec<AlbumPage>, now: DateTime<Local>) -> Vec<releases::Release> {
+    albums
+        .into_iter()
+        .map(|album| {
+            let details = releases::ReleaseDetails::from(&album);
+            releases::Release {
+                id: album.id,
+                title: album.title,
+                release_type: ReleaseType::Other,
+                class: None,
+                upcoming: details.is_upcoming(now),
+                details,
+            }
+        })
+        .unique_by(|release| release.id.clone())
+        .collect()
+}
+
+/// The artists' releases out of releases found for artists and labels.
+fn artist_releases(found: Vec<(Source, Vec<releases::Release>)>) -> Vec<NewReleases> {
+    found
+        .into_iter()
+        .filter_map(|(source, releases)| match source {
+            Source::Artist(artist) => Some(NewReleases {
+                artist: artist.clone(),
+                releases,
+            }),
+            Source::Label(_) => None,
+        })
+        .collect()
+}
+
+/// The artists an artist page lists as similar.
+fn similar_to(artist_page: &ArtistPage) -> Vec<artists::Artist> {
+    artist_page
+        .similar_artists
+        .items
+        .iter()
+        .filter(|similar| similar.id != artist_page.id)
+        .map(|similar| artists::Artist {
+            id: similar.id,
+            name: similar.name.display.clone(),
+        })
+        .unique_by(|artist| artist.id)
+        .collect()
+}
+
+/// Lets the user know about releases that were announced during a check.
+fn print_announced(name: &str, announced: &[releases::Release]) {
+    let release_msg = match announced.len() {
+        0 => return,
+        1 => "upcoming release",
+        _ => "upcoming releases",
+    };
+    println!("Found {} {release_msg} for {name}", announced.len());
+    for release in announced {
+        let stream_date = release.details.stream_date();
+        println!(
+            "  • {} ({})",
+            release.title,
+            stream_date.as_deref().unwrap_or("date unknown")
+        );
+    }
+}
+
+/// Removes a `#` comment from a line of a list, along with surrounding
+/// whitespace.
+fn strip_comment(line: &str) -> &str {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return "";
+    }
+    line.find(" #")
+        .or_else(|| line.find("\t#"))
+        .map_or(line, |i| &line[..i])
+        .trim()
+}
+
+/// A one line summary of an artist from search results, like
+/// `13925362  AVRALIZE · 12 albums · Metal`. Also used to list candidates
+/// when loading an artist by name.
+#[must_use]
+pub fn describe_artist(artist: &ArtistSummary) -> String {
+    let mut parts = vec![format!("{}  {}", artist.id, artist.name)];
+    if let Some(albums_count) = artist.albums_count {
+        let album_msg = if albums_count == 1 { "album" } else { "albums" };
+        parts.push(format!("{albums_count} {album_msg}"));
+    }
+    if let Some(genre) = &artist.genre {
+        parts.push(genre.name.clone());
+    }
+    parts.join(" · ")
+}
+
+/// A one line summary of a release, like
+/// `FREAKS (2024-03-22) · Arising Empire · Metal · 8 tracks · 34:48 · 24-bit/44.1 kHz · explicit`.
+fn describe_release(release: &releases::Release) -> String {
+    let details = &release.details;
+    let mut title = release.title.clone();
+    if let Some(release_date) = &details.release_date {
+        title = format!("{title} ({release_date})");
+    }
+
+    let tracks_count = details.tracks_count.map(|n| match n {
+        1 => "1 track".to_string(),
+        n => format!("{n} tracks"),
+    });
+    let duration = details.duration.map(format_duration);
+    let quality = details
+        .maximum_bit_depth
+        .zip(details.maximum_sampling_rate)
+        .map(|(bit_depth, sampling_rate)| format!("{bit_depth}-bit/{sampling_rate} kHz"));
+    let explicit = details
+        .parental_warning
+        .filter(|&warning| warning)
+        .map(|_| "explicit".to_string());
+
+    std::iter::once(title)
+        .chain(details.label_name.clone())
+        .chain(details.genre_name.clone())
+        .chain(tracks_count)
+        .chain(duration)
+        .chain(quality)
+        .chain(explicit)
+        .join(" · ")
+}
+
+/// Formats seconds as `h:mm:ss` or `m:ss`.
+fn format_duration(seconds: u32) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
     }
 }
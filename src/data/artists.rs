@@ -2,7 +2,7 @@ use rusqlite::OptionalExtension;
 
 use crate::data::db::Db;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Artist {
     pub id: u32,
     pub name: String,
@@ -13,6 +13,9 @@ select name from artists
 where id = ?1
 ";
 
+/// Gets the name of an artist by id.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn get_by_id(db: &Db, artist_id: u32) -> anyhow::Result<Option<String>> {
     let mut stmt = db.conn.prepare(GET_BY_ID)?;
@@ -21,10 +24,16 @@ pub fn get_by_id(db: &Db, artist_id: u32) -> anyhow::Result<Option<String>> {
 }
 
 const INSERT: &str = "
-insert into artists (id, name) values (?1, ?2)
-on conflict (id) do nothing;
+insert into artists (id, name, followed_at) values (?1, ?2, datetime('now', 'localtime'))
+on conflict (id) do update set
+    followed = true,
+    followed_at = case when followed then followed_at else excluded.followed_at end;
 ";
 
+/// Inserts an artist, following them again from now if they already exist
+/// but were unfollowed.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn insert(db: &Db, artist: &Artist) -> anyhow::Result<()> {
     db.conn.execute(INSERT, (&artist.id, &artist.name))?;
@@ -32,8 +41,11 @@ pub fn insert(db: &Db, artist: &Artist) -> anyhow::Result<()> {
     Ok(())
 }
 
-const GET_ALL: &str = "select id, name from artists;";
+const GET_ALL: &str = "select id, name from artists where followed;";
 
+/// Gets all the followed artists in the database.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn get_all(db: &Db) -> anyhow::Result<Vec<Artist>> {
     let mut stmt = db.conn.prepare(GET_ALL)?;
@@ -44,20 +56,130 @@ pub fn get_all(db: &Db) -> anyhow::Result<Vec<Artist>> {
         })
     })?;
 
-    let result = artists.map(|a| a.unwrap()).collect();
+    let result = artists.collect::<Result<_, _>>()?;
 
     Ok(result)
 }
 
+/// A followed artist and when they were followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Follow {
+    pub artist: Artist,
+    /// When the artist was followed, in local time. Unknown for artists
+    /// loaded before follow dates were recorded.
+    pub followed_at: Option<String>,
+}
+
+const GET_FOLLOWS: &str = "
+select id, name, followed_at from artists
+where followed
+order by id;
+";
+
+/// Gets all the followed artists and when they were followed.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_follows(db: &Db) -> anyhow::Result<Vec<Follow>> {
+    let mut stmt = db.conn.prepare(GET_FOLLOWS)?;
+    let follows = stmt.query_map([], |row| {
+        Ok(Follow {
+            artist: Artist {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            },
+            followed_at: row.get(2)?,
+        })
+    })?;
+    let result = follows.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const SET_FOLLOWED_AT: &str = "
+update artists set followed_at = ?2
+where id = ?1;
+";
+
+/// Sets when an artist was followed, e.g. when importing follows from
+/// another database.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn set_followed_at(db: &Db, artist_id: u32, followed_at: &str) -> anyhow::Result<()> {
+    db.conn.execute(SET_FOLLOWED_AT, (artist_id, followed_at))?;
+    Ok(())
+}
+
+const IS_FOLLOWED: &str = "
+select followed from artists
+where id = ?1
+";
+
+/// Whether an artist is in the database and still followed.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn is_followed(db: &Db, artist_id: u32) -> anyhow::Result<bool> {
+    let mut stmt = db.conn.prepare(IS_FOLLOWED)?;
+    let followed = stmt.query_one((artist_id,), |row| row.get(0)).optional()?;
+    Ok(followed.unwrap_or(false))
+}
+
+const UNFOLLOW: &str = "
+update artists set followed = false
+where id = ?1;
+";
+
+/// Stops checking an artist for new releases, keeping everything loaded for
+/// them.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn unfollow(db: &Db, artist_id: u32) -> anyhow::Result<()> {
+    db.conn.execute(UNFOLLOW, (artist_id,))?;
+    Ok(())
+}
+
+const DELETE_RELEASE_LINKS: &str = "
+delete from artists_2_releases
+where artist_id = ?1;
+";
+
+const DELETE_SIMILAR_ARTISTS: &str = "
+delete from similar_artists
+where artist_id = ?1;
+";
+
+const DELETE: &str = "
+delete from artists
+where id = ?1;
+";
+
+/// Deletes an artist, their links to releases and their similar artists.
+/// The releases themselves are left for [`releases::delete_orphans`](crate::data::releases::delete_orphans).
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn delete(db: &Db, artist_id: u32) -> anyhow::Result<()> {
+    db.conn.execute(DELETE_RELEASE_LINKS, (artist_id,))?;
+    db.conn.execute(DELETE_SIMILAR_ARTISTS, (artist_id,))?;
+    db.conn.execute(DELETE, (artist_id,))?;
+    Ok(())
+}
+
 const GET_ID_BY_NAME: &str = "
 select id from artists
-where lower(name) like $1;
+where lower(name) = lower(?1);
 ";
 
+/// Gets the id of an artist by name, ignoring case.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn get_id_by_name(db: &Db, artist: &str) -> anyhow::Result<Option<u32>> {
     let mut stmt = db.conn.prepare(GET_ID_BY_NAME)?;
-    let search_str = artist.trim().to_lowercase();
-    let id = stmt.query_row((search_str,), |row| row.get(0)).optional()?;
+    let id = stmt
+        .query_row((artist.trim(),), |row| row.get(0))
+        .optional()?;
     Ok(id)
 }
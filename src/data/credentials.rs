@@ -0,0 +1,82 @@
+use anyhow::Context;
+use rusqlite::OptionalExtension;
+
+use crate::data::db::Db;
+
+/// The credentials of the logged in user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub user_id: u64,
+    pub auth_token: String,
+}
+
+const GET: &str = "
+select user_id, auth_token from credentials
+where id = 1;
+";
+
+/// Gets the stored credentials, if someone has logged in.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get(db: &Db) -> anyhow::Result<Option<Credentials>> {
+    let mut stmt = db.conn.prepare(GET)?;
+    let credentials = stmt
+        .query_row([], |row| {
+            Ok(Credentials {
+                user_id: row.get(0)?,
+                auth_token: row.get(1)?,
+            })
+        })
+        .optional()?;
+    Ok(credentials)
+}
+
+const SET: &str = "
+insert into credentials (id, user_id, auth_token)
+values (1, ?1, ?2)
+on conflict (id) do update set
+    user_id = excluded.user_id,
+    auth_token = excluded.auth_token,
+    created_at = datetime('now', 'localtime');
+";
+
+/// Stores credentials, replacing any that were already stored. The token is
+/// stored as is, so the database file is made readable by its owner only.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db, credentials))]
+pub fn set(db: &Db, credentials: &Credentials) -> anyhow::Result<()> {
+    restrict_to_owner(db)?;
+    db.conn
+        .execute(SET, (credentials.user_id, &credentials.auth_token))?;
+    Ok(())
+}
+
+/// Makes the database file readable and writable by its owner only.
+/// In-memory databases are left alone.
+fn restrict_to_owner(db: &Db) -> anyhow::Result<()> {
+    let Some(path) = db.conn.path().filter(|path| !path.is_empty()) else {
+        return Ok(());
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("couldn't restrict access to {path}"))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+const CLEAR: &str = "delete from credentials;";
+
+/// Forgets the stored credentials. Returns whether there were any.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn clear(db: &Db) -> anyhow::Result<bool> {
+    let deleted = db.conn.execute(CLEAR, [])?;
+    Ok(deleted > 0)
+}
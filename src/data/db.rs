@@ -1,22 +1,137 @@
+use std::time::Duration;
+
+use anyhow::Context;
 use rusqlite::Connection;
 
+/// Where the database lives when `QOBUZ_DB_PATH` isn't set.
+pub const DEFAULT_DB_PATH: &str = "music.db3";
+
+/// How long to wait for another connection, like the response cache's, to
+/// finish writing before giving up. rusqlite happens to default to the same,
+/// but doesn't promise to.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
 pub struct Db {
     pub conn: Connection,
 }
 
 impl Db {
+    /// Opens (or creates) the database at `db_path`. Writes wait a while for
+    /// other connections to the same file rather than failing straight away.
+    /// # Errors
+    /// Will return `Err` if the database can't be opened.
     pub fn new(db_path: &str) -> anyhow::Result<Self> {
         let conn = Connection::open(db_path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
 
         rusqlite::vtab::array::load_module(&conn)?;
 
         Ok(Self { conn })
     }
+
+    /// Runs `f` in a transaction, which is committed if `f` succeeds and
+    /// rolled back if it fails. Anything done through this `Db` inside `f` is
+    /// part of the transaction.
+    /// # Errors
+    /// Will return `Err` if `f` does, or the transaction can't be started or
+    /// committed.
+    pub fn transaction<T>(&self, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let result = f()?;
+        tx.commit()?;
+        Ok(result)
+    }
 }
 
-pub const INIT_DB: &str = "
-begin;
+/// The database path from `QOBUZ_DB_PATH`, or [`DEFAULT_DB_PATH`].
+#[must_use]
+pub fn path_from_env() -> String {
+    std::env::var("QOBUZ_DB_PATH").unwrap_or(DEFAULT_DB_PATH.to_string())
+}
 
+/// A change to the database schema. The schema version (`PRAGMA
+/// user_version`) is the version of the last migration applied.
+#[derive(Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+/// All migrations, oldest first. Databases created before migrations existed
+/// are at version 0 but may already have some of these changes, so
+/// migrations need to cope with that.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        up: |conn| conn.execute_batch(INITIAL_SCHEMA),
+    },
+    Migration {
+        version: 2,
+        name: "credentials",
+        up: |conn| conn.execute_batch(CREDENTIALS),
+    },
+    Migration {
+        version: 3,
+        name: "http cache",
+        up: |conn| conn.execute_batch(HTTP_CACHE),
+    },
+    Migration {
+        version: 4,
+        name: "release details",
+        up: |conn| conn.execute_batch(RELEASE_DETAILS),
+    },
+    Migration {
+        version: 5,
+        name: "release classes",
+        up: |conn| add_column(conn, "releases", "class", "text"),
+    },
+    Migration {
+        version: 6,
+        name: "upcoming releases",
+        up: |conn| {
+            add_column(
+                conn,
+                "releases",
+                "upcoming",
+                "boolean not null default false",
+            )?;
+            add_column(conn, "releases", "verified_at", "timestamp")?;
+            add_column(conn, "release_details", "release_date_stream", "text")?;
+            add_column(conn, "release_details", "streamable_at", "integer")?;
+            add_column(conn, "release_details", "streamable", "boolean")
+        },
+    },
+    Migration {
+        version: 7,
+        name: "pending releases",
+        up: |conn| conn.execute_batch(PENDING_RELEASES),
+    },
+    Migration {
+        version: 8,
+        name: "unfollowed artists",
+        up: |conn| add_column(conn, "artists", "followed", "boolean not null default true"),
+    },
+    Migration {
+        version: 9,
+        name: "follow dates",
+        up: |conn| add_column(conn, "artists", "followed_at", "timestamp"),
+    },
+    Migration {
+        version: 10,
+        name: "labels",
+        up: |conn| conn.execute_batch(LABELS),
+    },
+    Migration {
+        version: 11,
+        name: "similar artists",
+        up: |conn| conn.execute_batch(SIMILAR_ARTISTS),
+    },
+];
+
+const INITIAL_SCHEMA: &str = "
 create table if not exists artists (
     id integer primary key,
     name text not null
@@ -73,11 +188,162 @@ create table if not exists playlists (
 );
 
 create index if not exists playlists__created_at on playlists (created_at);
+";
+
+const CREDENTIALS: &str = "
+create table if not exists credentials (
+    id integer primary key check (id = 1),
+    user_id integer not null,
+    auth_token text not null,
+    created_at timestamp default (datetime('now', 'localtime')) not null
+);
+";
+
+const HTTP_CACHE: &str = "
+create table if not exists http_cache (
+    key text primary key,
+    endpoint text not null,
+    body blob not null,
+    content_type text,
+    etag text,
+    last_modified text,
+    fetched_at integer not null
+);
+";
+
+const RELEASE_DETAILS: &str = "
+create table if not exists release_details (
+    release_id text primary key,
+    release_date text,
+    released_at integer,
+    upc text,
+    label_id integer,
+    label_name text,
+    genre_id integer,
+    genre_name text,
+    tracks_count integer,
+    duration integer,
+    maximum_bit_depth integer,
+    maximum_sampling_rate real,
+    parental_warning boolean,
+    image_small text,
+    image_thumbnail text,
+    image_large text,
+    foreign key (release_id) references releases (id)
+);
+";
+
+const PENDING_RELEASES: &str = "
+create table if not exists pending_releases (
+    release_id text primary key,
+    attempts integer not null,
+    first_attempt_at integer not null,
+    last_attempt_at integer not null,
+    foreign key (release_id) references releases (id)
+);
+";
 
-commit;
+const LABELS: &str = "
+create table if not exists labels (
+    id integer primary key,
+    name text not null,
+    followed_at timestamp default (datetime('now', 'localtime')) not null
+);
+
+create table if not exists labels_2_releases (
+    label_id integer not null,
+    release_id text not null,
+    primary key (label_id, release_id),
+    foreign key (label_id) references labels (id),
+    foreign key (release_id) references releases (id)
+);
 ";
 
+const SIMILAR_ARTISTS: &str = "
+create table if not exists similar_artists (
+    artist_id integer not null,
+    similar_artist_id integer not null,
+    similar_artist_name text not null,
+    primary key (artist_id, similar_artist_id),
+    foreign key (artist_id) references artists (id)
+);
+";
+
+/// Adds a column to a table, unless it's already there.
+fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare("select 1 from pragma_table_info(?1) where name = ?2")?;
+    if !stmt.exists((table, column))? {
+        conn.execute(
+            &format!("alter table {table} add column {column} {definition}"),
+            (),
+        )?;
+    }
+    Ok(())
+}
+
+/// The schema version this build expects.
+#[must_use]
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// The schema version of the database.
+/// # Errors
+/// Will return `Err` if there's an issue.
+pub fn schema_version(db: &Db) -> anyhow::Result<u32> {
+    let version = db
+        .conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version)
+}
+
+/// The migrations that haven't been applied to the database yet.
+/// # Errors
+/// Will return `Err` if the database is newer than this build or there's an
+/// issue.
+pub fn pending_migrations(db: &Db) -> anyhow::Result<&'static [Migration]> {
+    let version = schema_version(db)?;
+    let latest = latest_version();
+    if version > latest {
+        anyhow::bail!(
+            "the database schema is at version {version}, but this build only knows up to version {latest}"
+        );
+    }
+    Ok(&MIGRATIONS[MIGRATIONS.partition_point(|m| m.version <= version)..])
+}
+
+/// Applies any pending migrations, each in its own transaction, returning the
+/// ones that were applied.
+/// # Errors
+/// Will return `Err` if a migration fails, leaving the database at the last
+/// version that succeeded.
+#[tracing::instrument(skip(db))]
+pub fn migrate(db: &Db) -> anyhow::Result<Vec<&'static Migration>> {
+    let mut applied = vec![];
+    for migration in pending_migrations(db)? {
+        let tx = db.conn.unchecked_transaction()?;
+        (migration.up)(&tx).with_context(|| {
+            format!(
+                "migration {} ({}) failed",
+                migration.version, migration.name
+            )
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        applied.push(migration);
+    }
+    Ok(applied)
+}
+
+/// Brings the database schema up to date.
+/// # Errors
+/// Will return `Err` if there's an issue.
 pub fn init(db: &Db) -> anyhow::Result<()> {
-    db.conn.execute_batch(INIT_DB)?;
+    migrate(db)?;
     Ok(())
 }
@@ -0,0 +1,135 @@
+use rusqlite::OptionalExtension;
+
+use crate::data::db::Db;
+
+/// A cached API response.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub key: String,
+    pub endpoint: String,
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the response was fetched (or last revalidated), as a unix
+    /// timestamp.
+    pub fetched_at: i64,
+}
+
+/// How much is cached for an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStats {
+    pub endpoint: String,
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+const GET: &str = "
+select key, endpoint, body, content_type, etag, last_modified, fetched_at
+from http_cache
+where key = ?1;
+";
+
+/// Gets the cached response for a key.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get(db: &Db, key: &str) -> anyhow::Result<Option<CachedResponse>> {
+    let mut stmt = db.conn.prepare(GET)?;
+    let response = stmt
+        .query_row((key,), |row| {
+            Ok(CachedResponse {
+                key: row.get(0)?,
+                endpoint: row.get(1)?,
+                body: row.get(2)?,
+                content_type: row.get(3)?,
+                etag: row.get(4)?,
+                last_modified: row.get(5)?,
+                fetched_at: row.get(6)?,
+            })
+        })
+        .optional()?;
+    Ok(response)
+}
+
+const PUT: &str = "
+insert into http_cache (key, endpoint, body, content_type, etag, last_modified, fetched_at)
+values (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+on conflict (key) do update set
+    body = excluded.body,
+    content_type = excluded.content_type,
+    etag = excluded.etag,
+    last_modified = excluded.last_modified,
+    fetched_at = excluded.fetched_at;
+";
+
+/// Caches a response, replacing any cached response for the same key.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db, response), fields(key = response.key))]
+pub fn put(db: &Db, response: &CachedResponse) -> anyhow::Result<()> {
+    db.conn.execute(
+        PUT,
+        (
+            &response.key,
+            &response.endpoint,
+            &response.body,
+            &response.content_type,
+            &response.etag,
+            &response.last_modified,
+            response.fetched_at,
+        ),
+    )?;
+    Ok(())
+}
+
+const TOUCH: &str = "
+update http_cache
+set fetched_at = ?2
+where key = ?1;
+";
+
+/// Marks a cached response as fetched at the given time, after the server
+/// said it hasn't changed.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn touch(db: &Db, key: &str, fetched_at: i64) -> anyhow::Result<()> {
+    db.conn.execute(TOUCH, (key, fetched_at))?;
+    Ok(())
+}
+
+const STATS: &str = "
+select endpoint, count(*), sum(length(body))
+from http_cache
+group by endpoint
+order by endpoint;
+";
+
+/// Gets how much is cached for each endpoint.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn stats(db: &Db) -> anyhow::Result<Vec<EndpointStats>> {
+    let mut stmt = db.conn.prepare(STATS)?;
+    let stats = stmt.query_map([], |row| {
+        Ok(EndpointStats {
+            endpoint: row.get(0)?,
+            entries: row.get(1)?,
+            bytes: row.get(2)?,
+        })
+    })?;
+    let result = stats.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const CLEAR: &str = "delete from http_cache;";
+
+/// Removes every cached response. Returns how many there were.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn clear(db: &Db) -> anyhow::Result<usize> {
+    let deleted = db.conn.execute(CLEAR, [])?;
+    Ok(deleted)
+}
@@ -0,0 +1,104 @@
+use rusqlite::OptionalExtension;
+
+use crate::data::db::Db;
+
+/// A record label whose releases are checked like a followed artist's.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub id: u64,
+    pub name: String,
+}
+
+const GET_BY_ID: &str = "
+select name from labels
+where id = ?1
+";
+
+/// Gets the name of a followed label by id.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_by_id(db: &Db, label_id: u64) -> anyhow::Result<Option<String>> {
+    let mut stmt = db.conn.prepare(GET_BY_ID)?;
+    let label = stmt.query_one((label_id,), |row| row.get(0)).optional()?;
+    Ok(label)
+}
+
+const INSERT: &str = "
+insert into labels (id, name) values (?1, ?2)
+on conflict (id) do nothing;
+";
+
+/// Inserts a label, doing nothing if it's already followed.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn insert(db: &Db, label: &Label) -> anyhow::Result<()> {
+    db.conn.execute(INSERT, (&label.id, &label.name))?;
+    Ok(())
+}
+
+const GET_ALL: &str = "select id, name from labels;";
+
+/// Gets all the followed labels.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_all(db: &Db) -> anyhow::Result<Vec<Label>> {
+    let mut stmt = db.conn.prepare(GET_ALL)?;
+    let labels = stmt.query_map([], |row| {
+        Ok(Label {
+            id: row.get(0)?,
+            name: row.get(1)?,
+        })
+    })?;
+    let result = labels.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+/// A followed label and when it was followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Follow {
+    pub label: Label,
+    /// When the label was followed, in local time.
+    pub followed_at: String,
+}
+
+const GET_FOLLOWS: &str = "
+select id, name, followed_at from labels
+order by id;
+";
+
+/// Gets all the followed labels and when they were followed.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_follows(db: &Db) -> anyhow::Result<Vec<Follow>> {
+    let mut stmt = db.conn.prepare(GET_FOLLOWS)?;
+    let follows = stmt.query_map([], |row| {
+        Ok(Follow {
+            label: Label {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            },
+            followed_at: row.get(2)?,
+        })
+    })?;
+    let result = follows.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const SET_FOLLOWED_AT: &str = "
+update labels set followed_at = ?2
+where id = ?1;
+";
+
+/// Sets when a label was followed, e.g. when importing follows from another
+/// database.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn set_followed_at(db: &Db, label_id: u64, followed_at: &str) -> anyhow::Result<()> {
+    db.conn.execute(SET_FOLLOWED_AT, (label_id, followed_at))?;
+    Ok(())
+}
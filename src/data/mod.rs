@@ -1,5 +1,10 @@
 pub mod artists;
+pub mod credentials;
 pub mod db;
+pub mod http_cache;
+pub mod labels;
+pub mod pending;
 pub mod playlists;
 pub mod releases;
+pub mod similar_artists;
 pub mod tracks;
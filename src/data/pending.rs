@@ -0,0 +1,103 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use crate::data::db::Db;
+
+/// A release found during a check that couldn't be loaded yet, because it
+/// 404'd or had no tracks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRelease {
+    pub release_id: String,
+    /// How many times loading the release has failed.
+    pub attempts: u32,
+    /// When loading the release first failed, as a unix timestamp.
+    pub first_attempt_at: i64,
+    /// When loading the release last failed, as a unix timestamp.
+    pub last_attempt_at: i64,
+}
+
+impl PendingRelease {
+    /// When the release should next be retried, waiting twice as long after
+    /// each failed attempt.
+    #[must_use]
+    pub fn next_attempt_at(&self, base_delay: Duration, max_delay: Duration) -> i64 {
+        let exponent = self.attempts.saturating_sub(1).min(31);
+        let delay = base_delay.saturating_mul(1 << exponent).min(max_delay);
+        self.last_attempt_at + i64::try_from(delay.as_secs()).unwrap_or(i64::MAX)
+    }
+
+    /// Whether the release has been failing to load for longer than
+    /// `max_age`, and isn't worth retrying automatically any more.
+    #[must_use]
+    pub fn is_expired(&self, now: i64, max_age: Duration) -> bool {
+        now - self.first_attempt_at > i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX)
+    }
+}
+
+const GET_ALL: &str = "
+select release_id, attempts, first_attempt_at, last_attempt_at
+from pending_releases;
+";
+
+/// Gets all the pending releases, by release id.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_all(db: &Db) -> anyhow::Result<HashMap<String, PendingRelease>> {
+    let mut stmt = db.conn.prepare(GET_ALL)?;
+    let pending = stmt.query_map([], |row| {
+        Ok(PendingRelease {
+            release_id: row.get(0)?,
+            attempts: row.get(1)?,
+            first_attempt_at: row.get(2)?,
+            last_attempt_at: row.get(3)?,
+        })
+    })?;
+    let result = pending
+        .map(|p| p.map(|p| (p.release_id.clone(), p)))
+        .collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const RECORD_ATTEMPT: &str = "
+insert into pending_releases (release_id, attempts, first_attempt_at, last_attempt_at)
+values (?1, 1, ?2, ?2)
+on conflict (release_id) do update set
+    attempts = attempts + 1,
+    last_attempt_at = excluded.last_attempt_at;
+";
+
+/// Records a failed attempt to load releases at `now`, a unix timestamp.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn record_attempt(db: &Db, release_ids: &[String], now: i64) -> anyhow::Result<()> {
+    let mut stmt = db.conn.prepare_cached(RECORD_ATTEMPT)?;
+    for release_id in release_ids {
+        stmt.execute((release_id, now))?;
+    }
+    Ok(())
+}
+
+const REMOVE: &str = "
+delete from pending_releases
+where release_id in (
+    select value from rarray(?1)
+);
+";
+
+/// Stops retrying releases, returning how many were pending.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn remove(db: &Db, release_ids: &[String]) -> anyhow::Result<usize> {
+    let mut stmt = db.conn.prepare(REMOVE)?;
+    let values = Rc::new(
+        release_ids
+            .iter()
+            .cloned()
+            .map(rusqlite::types::Value::from)
+            .collect::<Vec<_>>(),
+    );
+    let removed = stmt.execute([values])?;
+    Ok(removed)
+}
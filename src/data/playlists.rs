@@ -1,6 +1,6 @@
 use crate::data::db::Db;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Playlist {
     pub id: u32,
     pub name: String,
@@ -12,6 +12,9 @@ values (?1, ?2)
 on conflict (id) do nothing;
 ";
 
+/// Records a playlist that was created.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn insert(db: &Db, playlist: &Playlist) -> anyhow::Result<()> {
     let Playlist { id, name, .. } = playlist;
@@ -1,6 +1,11 @@
-use std::rc::Rc;
+use std::{collections::HashSet, rc::Rc};
 
-use crate::types::ReleaseType;
+use chrono::{DateTime, Local};
+
+use crate::{
+    api::models,
+    types::{ReleaseClass, ReleaseType},
+};
 
 use crate::data::db::Db;
 
@@ -10,15 +15,169 @@ pub struct Release {
     pub id: String,
     pub title: String,
     pub release_type: ReleaseType,
+    /// How the release was classified when a check found it. Releases loaded
+    /// with the rest of an artist's discography aren't classified.
+    pub class: Option<ReleaseClass>,
+    /// Whether the release has been announced but can't be streamed yet.
+    pub upcoming: bool,
+    pub details: ReleaseDetails,
+}
+
+/// What's known about a release beyond its title. Artist pages only carry
+/// some of this, so the rest is filled in from the release itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReleaseDetails {
+    /// The original release date, as `YYYY-MM-DD`.
+    pub release_date: Option<String>,
+    /// When the release came out, as a unix timestamp.
+    pub released_at: Option<i64>,
+    pub upc: Option<String>,
+    pub label_id: Option<u64>,
+    pub label_name: Option<String>,
+    pub genre_id: Option<u64>,
+    pub genre_name: Option<String>,
+    pub tracks_count: Option<u32>,
+    /// The length of the release in seconds.
+    pub duration: Option<u32>,
+    pub maximum_bit_depth: Option<u32>,
+    /// The highest sampling rate in kHz.
+    pub maximum_sampling_rate: Option<f64>,
+    pub parental_warning: Option<bool>,
+    pub image_small: Option<String>,
+    pub image_thumbnail: Option<String>,
+    pub image_large: Option<String>,
+    /// When the release can be streamed, as `YYYY-MM-DD`.
+    pub release_date_stream: Option<String>,
+    /// When the release can be streamed, as a unix timestamp.
+    pub streamable_at: Option<i64>,
+    pub streamable: Option<bool>,
+}
+
+impl ReleaseDetails {
+    /// Fills in anything missing from `self` with what's in `other`.
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            release_date: self.release_date.or(other.release_date),
+            released_at: self.released_at.or(other.released_at),
+            upc: self.upc.or(other.upc),
+            label_id: self.label_id.or(other.label_id),
+            label_name: self.label_name.or(other.label_name),
+            genre_id: self.genre_id.or(other.genre_id),
+            genre_name: self.genre_name.or(other.genre_name),
+            tracks_count: self.tracks_count.or(other.tracks_count),
+            duration: self.duration.or(other.duration),
+            maximum_bit_depth: self.maximum_bit_depth.or(other.maximum_bit_depth),
+            maximum_sampling_rate: self.maximum_sampling_rate.or(other.maximum_sampling_rate),
+            parental_warning: self.parental_warning.or(other.parental_warning),
+            image_small: self.image_small.or(other.image_small),
+            image_thumbnail: self.image_thumbnail.or(other.image_thumbnail),
+            image_large: self.image_large.or(other.image_large),
+            release_date_stream: self.release_date_stream.or(other.release_date_stream),
+            streamable_at: self.streamable_at.or(other.streamable_at),
+            streamable: self.streamable.or(other.streamable),
+        }
+    }
+
+    /// Whether the release has been announced but can't be streamed until
+    /// some time after `now`. Releases that can't be streamed and have no
+    /// date they can be aren't upcoming, they're just unavailable.
+    #[must_use]
+    pub fn is_upcoming(&self, now: DateTime<Local>) -> bool {
+        if self.streamable != Some(false) {
+            return false;
+        }
+
+        let today = now.date_naive().format("%Y-%m-%d").to_string();
+        self.streamable_at.is_some_and(|at| at > now.timestamp())
+            || self
+                .release_date_stream
+                .as_ref()
+                .is_some_and(|date| *date > today)
+    }
+
+    /// When the release can be streamed, as `YYYY-MM-DD`.
+    #[must_use]
+    pub fn stream_date(&self) -> Option<String> {
+        self.release_date_stream.clone().or_else(|| {
+            self.streamable_at
+                .and_then(|at| DateTime::from_timestamp(at, 0))
+                .map(|at| at.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        })
+    }
+}
+
+impl From<&models::Release> for ReleaseDetails {
+    fn from(release: &models::Release) -> Self {
+        let dates = release.dates.as_ref();
+        let audio_info = release.audio_info.as_ref();
+        let image = release.image.as_ref();
+        Self {
+            release_date: dates.and_then(|d| d.original.clone()),
+            released_at: None,
+            upc: None,
+            label_id: release.label.as_ref().map(|l| l.id),
+            label_name: release.label.as_ref().map(|l| l.name.clone()),
+            genre_id: release.genre.as_ref().map(|g| g.id),
+            genre_name: release.genre.as_ref().map(|g| g.name.clone()),
+            tracks_count: release.tracks_count,
+            duration: release.duration,
+            maximum_bit_depth: audio_info.and_then(|a| a.maximum_bit_depth),
+            maximum_sampling_rate: audio_info.and_then(|a| a.maximum_sampling_rate),
+            parental_warning: release.parental_warning,
+            image_small: image.and_then(|i| i.small.clone()),
+            image_thumbnail: image.and_then(|i| i.thumbnail.clone()),
+            image_large: image.and_then(|i| i.large.clone()),
+            release_date_stream: dates.and_then(|d| d.stream.clone()),
+            streamable_at: None,
+            streamable: release.rights.as_ref().and_then(|r| r.streamable),
+        }
+    }
+}
+
+impl From<&models::AlbumPage> for ReleaseDetails {
+    fn from(album: &models::AlbumPage) -> Self {
+        let image = album.image.as_ref();
+        Self {
+            release_date: album.release_date_original.clone(),
+            released_at: album.released_at,
+            upc: album.upc.clone(),
+            label_id: album.label.as_ref().map(|l| l.id),
+            label_name: album.label.as_ref().map(|l| l.name.clone()),
+            genre_id: album.genre.as_ref().map(|g| g.id),
+            genre_name: album.genre.as_ref().map(|g| g.name.clone()),
+            tracks_count: album.tracks_count,
+            duration: album.duration,
+            maximum_bit_depth: album.maximum_bit_depth,
+            maximum_sampling_rate: album.maximum_sampling_rate,
+            parental_warning: album.parental_warning,
+            image_small: image.and_then(|i| i.small.clone()),
+            image_thumbnail: image.and_then(|i| i.thumbnail.clone()),
+            image_large: image.and_then(|i| i.large.clone()),
+            release_date_stream: album.release_date_stream.clone(),
+            streamable_at: album.streamable_at,
+            streamable: album.streamable,
+        }
+    }
 }
 
 const INSERT_RELEASE: &str = "
 insert into releases (
     id,
     title,
-    release_type_id
-) values (?1, ?2, ?3)
-on conflict (id) do nothing;
+    release_type_id,
+    class,
+    upcoming
+) values (?1, ?2, ?3, ?4, ?5)
+on conflict (id) do update set
+    -- Label releases are stored as 'Other' until an artist page says what
+    -- they are.
+    release_type_id = case
+        when release_type_id = 'Other' then excluded.release_type_id
+        else release_type_id
+    end,
+    class = coalesce(excluded.class, class),
+    upcoming = excluded.upcoming;
 ";
 
 const INSERT_ARTIST_2_RELEASE: &str = "
@@ -27,49 +186,285 @@ values (?1, ?2)
 on conflict (artist_id, release_id) do nothing;
 ";
 
+const INSERT_LABEL_2_RELEASE: &str = "
+insert into labels_2_releases (label_id, release_id)
+values (?1, ?2)
+on conflict (label_id, release_id) do nothing;
+";
+
+/// Inserts releases and their details, and links them to an artist.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn insert_batch(db: &Db, artist_id: u32, releases: &[Release]) -> anyhow::Result<()> {
-    let mut release_stmt = db.conn.prepare(INSERT_RELEASE)?;
-    let mut artist_2_release_stmt = db.conn.prepare(INSERT_ARTIST_2_RELEASE)?;
+    insert_releases(db, releases)?;
+    let mut artist_2_release_stmt = db.conn.prepare_cached(INSERT_ARTIST_2_RELEASE)?;
+    for release in releases {
+        artist_2_release_stmt.execute((artist_id, &release.id))?;
+    }
+    Ok(())
+}
+
+const FILL_IN_RELEASE_TYPE: &str = "
+update releases set release_type_id = ?2
+where id = ?1 and release_type_id = 'Other';
+";
+
+/// Links releases that are already stored to an artist, filling in their
+/// type if it wasn't known (e.g. they were found for a label).
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db, releases))]
+pub fn link_to_artist(db: &Db, artist_id: u32, releases: &[Release]) -> anyhow::Result<()> {
+    let mut artist_2_release_stmt = db.conn.prepare_cached(INSERT_ARTIST_2_RELEASE)?;
+    let mut release_type_stmt = db.conn.prepare_cached(FILL_IN_RELEASE_TYPE)?;
+    for release in releases {
+        artist_2_release_stmt.execute((artist_id, &release.id))?;
+        release_type_stmt.execute((&release.id, release.release_type))?;
+    }
+    Ok(())
+}
+
+/// Inserts releases and their details, and links them to a label.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn insert_batch_for_label(db: &Db, label_id: u64, releases: &[Release]) -> anyhow::Result<()> {
+    insert_releases(db, releases)?;
+    link_to_label(db, label_id, releases.iter().map(|r| r.id.as_str()))
+}
+
+/// Links releases that are already stored to a label.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db, release_ids))]
+pub fn link_to_label<'a>(
+    db: &Db,
+    label_id: u64,
+    release_ids: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<()> {
+    let mut label_2_release_stmt = db.conn.prepare_cached(INSERT_LABEL_2_RELEASE)?;
+    for release_id in release_ids {
+        label_2_release_stmt.execute((label_id, release_id))?;
+    }
+    Ok(())
+}
+
+fn insert_releases(db: &Db, releases: &[Release]) -> anyhow::Result<()> {
+    let mut release_stmt = db.conn.prepare_cached(INSERT_RELEASE)?;
     for release in releases {
         release_stmt.execute((
-            release.id.clone(),
-            release.title.clone(),
+            &release.id,
+            &release.title,
             release.release_type,
+            release.class,
+            release.upcoming,
         ))?;
-        artist_2_release_stmt.execute((artist_id, release.id.clone()))?;
+        upsert_details(db, &release.id, &release.details)?;
     }
     Ok(())
 }
 
-const GET_ALL_FOR_ARTIST: &str = "
-select id, title, release_type_id from releases r
-join artists_2_releases a2r on a2r.release_id = r.id
-where a2r.artist_id = ?1;
+const UPSERT_DETAILS: &str = "
+insert into release_details (
+    release_id,
+    release_date,
+    released_at,
+    upc,
+    label_id,
+    label_name,
+    genre_id,
+    genre_name,
+    tracks_count,
+    duration,
+    maximum_bit_depth,
+    maximum_sampling_rate,
+    parental_warning,
+    image_small,
+    image_thumbnail,
+    image_large,
+    release_date_stream,
+    streamable_at,
+    streamable
+) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
+on conflict (release_id) do update set
+    release_date = coalesce(excluded.release_date, release_date),
+    released_at = coalesce(excluded.released_at, released_at),
+    upc = coalesce(excluded.upc, upc),
+    label_id = coalesce(excluded.label_id, label_id),
+    label_name = coalesce(excluded.label_name, label_name),
+    genre_id = coalesce(excluded.genre_id, genre_id),
+    genre_name = coalesce(excluded.genre_name, genre_name),
+    tracks_count = coalesce(excluded.tracks_count, tracks_count),
+    duration = coalesce(excluded.duration, duration),
+    maximum_bit_depth = coalesce(excluded.maximum_bit_depth, maximum_bit_depth),
+    maximum_sampling_rate = coalesce(excluded.maximum_sampling_rate, maximum_sampling_rate),
+    parental_warning = coalesce(excluded.parental_warning, parental_warning),
+    image_small = coalesce(excluded.image_small, image_small),
+    image_thumbnail = coalesce(excluded.image_thumbnail, image_thumbnail),
+    image_large = coalesce(excluded.image_large, image_large),
+    release_date_stream = coalesce(excluded.release_date_stream, release_date_stream),
+    streamable_at = coalesce(excluded.streamable_at, streamable_at),
+    streamable = coalesce(excluded.streamable, streamable);
 ";
 
+/// Stores what's known about a release. Details that are already stored are
+/// kept unless `details` has a newer value for them.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn upsert_details(db: &Db, release_id: &str, details: &ReleaseDetails) -> anyhow::Result<()> {
+    let mut stmt = db.conn.prepare_cached(UPSERT_DETAILS)?;
+    stmt.execute(rusqlite::params![
+        release_id,
+        details.release_date,
+        details.released_at,
+        details.upc,
+        details.label_id,
+        details.label_name,
+        details.genre_id,
+        details.genre_name,
+        details.tracks_count,
+        details.duration,
+        details.maximum_bit_depth,
+        details.maximum_sampling_rate,
+        details.parental_warning,
+        details.image_small,
+        details.image_thumbnail,
+        details.image_large,
+        details.release_date_stream,
+        details.streamable_at,
+        details.streamable,
+    ])?;
+    Ok(())
+}
+
+/// Selects releases with their details, to be read by [`from_row`].
+macro_rules! select_releases {
+    () => {
+        "
+select
+    r.id,
+    r.title,
+    r.release_type_id,
+    r.class,
+    r.upcoming,
+    d.release_date,
+    d.released_at,
+    d.upc,
+    d.label_id,
+    d.label_name,
+    d.genre_id,
+    d.genre_name,
+    d.tracks_count,
+    d.duration,
+    d.maximum_bit_depth,
+    d.maximum_sampling_rate,
+    d.parental_warning,
+    d.image_small,
+    d.image_thumbnail,
+    d.image_large,
+    d.release_date_stream,
+    d.streamable_at,
+    d.streamable
+from releases r
+left join release_details d on d.release_id = r.id
+"
+    };
+}
+
+fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Release> {
+    Ok(Release {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        release_type: row.get(2)?,
+        class: row.get(3)?,
+        upcoming: row.get(4)?,
+        details: ReleaseDetails {
+            release_date: row.get(5)?,
+            released_at: row.get(6)?,
+            upc: row.get(7)?,
+            label_id: row.get(8)?,
+            label_name: row.get(9)?,
+            genre_id: row.get(10)?,
+            genre_name: row.get(11)?,
+            tracks_count: row.get(12)?,
+            duration: row.get(13)?,
+            maximum_bit_depth: row.get(14)?,
+            maximum_sampling_rate: row.get(15)?,
+            parental_warning: row.get(16)?,
+            image_small: row.get(17)?,
+            image_thumbnail: row.get(18)?,
+            image_large: row.get(19)?,
+            release_date_stream: row.get(20)?,
+            streamable_at: row.get(21)?,
+            streamable: row.get(22)?,
+        },
+    })
+}
+
+const GET_ALL_FOR_ARTIST: &str = concat!(
+    select_releases!(),
+    "join artists_2_releases a2r on a2r.release_id = r.id
+where a2r.artist_id = ?1;"
+);
+
+/// Gets all the releases for an artist.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn get_all_for_artist(db: &Db, artist_id: u32) -> anyhow::Result<Vec<Release>> {
     let mut stmt = db.conn.prepare(GET_ALL_FOR_ARTIST)?;
-    let releases = stmt.query_map((artist_id,), |row| {
-        Ok(Release {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            release_type: row.get(2)?,
-        })
-    })?;
-    let result = releases.map(|a| a.unwrap()).collect();
+    let releases = stmt.query_map((artist_id,), from_row)?;
+    let result = releases.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const GET_ALL_FOR_LABEL: &str = concat!(
+    select_releases!(),
+    "join labels_2_releases l2r on l2r.release_id = r.id
+where l2r.label_id = ?1;"
+);
+
+/// Gets all the releases for a label.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_all_for_label(db: &Db, label_id: u64) -> anyhow::Result<Vec<Release>> {
+    let mut stmt = db.conn.prepare(GET_ALL_FOR_LABEL)?;
+    let releases = stmt.query_map((label_id,), from_row)?;
+    let result = releases.collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+const GET_ALL_IDS: &str = "select id from releases;";
+
+/// Gets the ids of all the stored releases.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_all_ids(db: &Db) -> anyhow::Result<HashSet<String>> {
+    let mut stmt = db.conn.prepare(GET_ALL_IDS)?;
+    let ids = stmt.query_map([], |row| row.get(0))?;
+    let result = ids.collect::<Result<_, _>>()?;
     Ok(result)
 }
 
 const BULK_VERIFY: &str = "
 update releases
-set verified = true
+set
+    verified = true,
+    upcoming = false,
+    verified_at = datetime('now', 'localtime')
 where id in (
     select value from rarray(?1)
 );
 ";
 
+/// Marks releases as verified, including upcoming releases that can now be
+/// streamed.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn bulk_verify(db: &Db, release_ids: &[String]) -> anyhow::Result<()> {
     let mut stmt = db.conn.prepare(BULK_VERIFY)?;
@@ -83,3 +478,35 @@ pub fn bulk_verify(db: &Db, release_ids: &[String]) -> anyhow::Result<()> {
     stmt.execute([values])?;
     Ok(())
 }
+
+const GET_ORPHANS: &str = "
+select id from releases
+where id not in (select release_id from artists_2_releases)
+    and id not in (select release_id from labels_2_releases);
+";
+
+const DELETE_ORPHANS: &[&str] = &[
+    "delete from pending_releases where release_id in (select value from rarray(?1));",
+    "delete from release_details where release_id in (select value from rarray(?1));",
+    "delete from tracks_2_releases where release_id in (select value from rarray(?1));",
+    "delete from releases where id in (select value from rarray(?1));",
+];
+
+/// Deletes releases that no artist or label links to any more, along with
+/// everything stored about them. Returns how many releases were deleted.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn delete_orphans(db: &Db) -> anyhow::Result<usize> {
+    let mut stmt = db.conn.prepare(GET_ORPHANS)?;
+    let orphans = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .map(|id| id.map(rusqlite::types::Value::from))
+        .collect::<Result<Vec<_>, _>>()?;
+    let deleted = orphans.len();
+    let values = Rc::new(orphans);
+    for sql in DELETE_ORPHANS {
+        db.conn.execute(sql, [&values])?;
+    }
+    Ok(deleted)
+}
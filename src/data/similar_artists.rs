@@ -0,0 +1,82 @@
+use crate::data::{artists::Artist, db::Db};
+
+/// An artist who isn't followed, but is similar to some who are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recommendation {
+    pub artist: Artist,
+    /// The followed artists they're similar to, by name.
+    pub similar_to: Vec<String>,
+}
+
+const DELETE_FOR_ARTIST: &str = "
+delete from similar_artists
+where artist_id = ?1;
+";
+
+const INSERT: &str = "
+insert into similar_artists (artist_id, similar_artist_id, similar_artist_name) values (?1, ?2, ?3)
+on conflict (artist_id, similar_artist_id) do update set similar_artist_name = excluded.similar_artist_name;
+";
+
+/// Replaces the artists similar to an artist with the ones from their latest
+/// artist page.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db, similar))]
+pub fn replace(db: &Db, artist_id: u32, similar: &[Artist]) -> anyhow::Result<()> {
+    db.conn
+        .prepare_cached(DELETE_FOR_ARTIST)?
+        .execute((artist_id,))?;
+    let mut stmt = db.conn.prepare_cached(INSERT)?;
+    for artist in similar {
+        stmt.execute((artist_id, artist.id, &artist.name))?;
+    }
+    Ok(())
+}
+
+// Names can differ between pages, so the most common one wins.
+const GET_RECOMMENDATIONS: &str = "
+select
+    s.similar_artist_id,
+    (
+        select similar_artist_name from similar_artists
+        where similar_artist_id = s.similar_artist_id
+        group by similar_artist_name
+        order by count(*) desc, similar_artist_name
+        limit 1
+    ),
+    json_group_array(a.name)
+from similar_artists s
+join artists a on a.id = s.artist_id and a.followed
+where s.similar_artist_id not in (select id from artists where followed)
+group by s.similar_artist_id
+order by count(*) desc, 2, 1;
+";
+
+/// Gets the artists that aren't followed but are similar to followed ones,
+/// ranked by how many followed artists they're similar to.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn get_recommendations(db: &Db) -> anyhow::Result<Vec<Recommendation>> {
+    let mut stmt = db.conn.prepare(GET_RECOMMENDATIONS)?;
+    let recommendations = stmt.query_map([], |row| {
+        let similar_to: String = row.get(2)?;
+        Ok((
+            Artist {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            },
+            similar_to,
+        ))
+    })?;
+
+    let mut result = Vec::new();
+    for recommendation in recommendations {
+        let (artist, similar_to) = recommendation?;
+        let mut similar_to: Vec<String> = serde_json::from_str(&similar_to)?;
+        similar_to.sort_unstable();
+        result.push(Recommendation { artist, similar_to });
+    }
+    Ok(result)
+}
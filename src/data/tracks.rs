@@ -1,4 +1,6 @@
-use crate::{api::models::Track, data::db::Db};
+use std::rc::Rc;
+
+use crate::{api::models::Track, data::db::Db, types::ReleaseClass};
 
 const INSERT_TRACK: &str = "
 insert into tracks (id, title)
@@ -12,10 +14,13 @@ values (?1, ?2)
 on conflict (release_id, track_id) do nothing;
 ";
 
+/// Inserts tracks and links them to a release.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
 pub fn insert_batch(db: &Db, release_id: &str, tracks: Vec<Track>) -> anyhow::Result<()> {
-    let mut track_stmt = db.conn.prepare(INSERT_TRACK)?;
-    let mut track_2_release_stmt = db.conn.prepare(INSERT_TRACK_2_RELEASE)?;
+    let mut track_stmt = db.conn.prepare_cached(INSERT_TRACK)?;
+    let mut track_2_release_stmt = db.conn.prepare_cached(INSERT_TRACK_2_RELEASE)?;
     for track in tracks {
         track_stmt.execute((track.id, track.title))?;
         track_2_release_stmt.execute((release_id, track.id))?;
@@ -27,18 +32,48 @@ const GET_LATEST: &str = "
 select t.id from tracks t
 join tracks_2_releases t2r on t2r.track_id = t.id
 join releases r on r.id = t2r.release_id
-where r.created_at >= (
-    select created_at from playlists
-    order by created_at desc
-    limit 1
+where coalesce(r.verified_at, r.created_at) >= coalesce(
+    (
+        select created_at from playlists
+        order by created_at desc
+        limit 1
+    ),
+    0
+)
+and (
+    r.class is null
+    or r.class not in (select value from rarray(?1))
 );
 ";
 
-/// Gets all tracks that haven't been loaded into a playlist.
+/// Gets all tracks that haven't been loaded into a playlist, leaving out
+/// tracks from releases of the `exclude`d classes.
+/// # Errors
+/// Will return `Err` if there's an issue.
 #[tracing::instrument(skip(db))]
-pub fn get_latest(db: &Db) -> anyhow::Result<Vec<u32>> {
+pub fn get_latest(db: &Db, exclude: &[ReleaseClass]) -> anyhow::Result<Vec<u32>> {
     let mut stmt = db.conn.prepare(GET_LATEST)?;
-    let latest_tracks = stmt.query_map([], |row| row.get(0)).unwrap();
-    let latest_track_ids = latest_tracks.map(|a| a.unwrap()).collect();
+    let exclude = Rc::new(
+        exclude
+            .iter()
+            .map(|class| rusqlite::types::Value::from(class.to_str().to_string()))
+            .collect::<Vec<_>>(),
+    );
+    let latest_tracks = stmt.query_map([exclude], |row| row.get(0))?;
+    let latest_track_ids = latest_tracks.collect::<Result<_, _>>()?;
     Ok(latest_track_ids)
 }
+
+const DELETE_ORPHANS: &str = "
+delete from tracks
+where id not in (select track_id from tracks_2_releases);
+";
+
+/// Deletes tracks that aren't on any release, returning how many there were.
+/// # Errors
+/// Will return `Err` if there's an issue.
+#[tracing::instrument(skip(db))]
+pub fn delete_orphans(db: &Db) -> anyhow::Result<usize> {
+    let deleted = db.conn.execute(DELETE_ORPHANS, ())?;
+    Ok(deleted)
+}
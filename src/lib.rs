@@ -0,0 +1,17 @@
+//! Check for new music from artists you care about on Qobuz.
+//!
+//! The crate is split into a catalog client ([`Api`]), local storage
+//! ([`data`]) and the [`App`] that ties them together to check for new
+//! releases and generate playlists. The `qobuz` binary is a thin CLI over
+//! this library.
+
+pub mod api;
+pub mod app;
+pub mod data;
+pub mod logging;
+pub mod types;
+
+pub use api::Api;
+pub use app::App;
+pub use data::db::Db;
+pub use types::ReleaseType;
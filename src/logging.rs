@@ -28,6 +28,8 @@ where
 /// Register a subscriber as global default to process span data.
 ///
 /// It should only be called once.
+/// # Panics
+/// Will panic if a global subscriber has already been set.
 pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
     // Redirect all `log`'s events to our subscriber.
     LogTracer::init().expect("Failed to set logger");
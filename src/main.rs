@@ -1,11 +1,18 @@
-use app::App;
-use clap::Parser;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-mod api;
-pub mod app;
-mod data;
-mod logging;
-mod types;
+use anyhow::Context;
+use clap::Parser;
+use qobuz::{
+    App, Db,
+    api::{ApiError, models::ArtistSummary},
+    app::{self, CheckOptions, PendingRetry, PlaylistOptions},
+    data, logging,
+    types::{FollowList, LinkError, QobuzLink, ReleaseClass},
+};
 
 #[derive(Debug, clap::Parser)]
 pub struct Cli {
@@ -16,39 +23,372 @@ pub struct Cli {
 #[derive(Debug, clap::Subcommand)]
 pub enum Command {
     /// Load an artist's releases into the database.
-    Load { artist_id: u32 },
-    /// Check for new music from all the artists in the database.
-    Check,
+    #[command(group(clap::ArgGroup::new("source").required(true)))]
+    Load {
+        /// An artist id or Qobuz artist link, or `-` to read a list of them
+        /// from stdin.
+        #[arg(group = "source")]
+        artist: Option<String>,
+        /// Search for the artist by name instead.
+        #[arg(long, group = "source")]
+        name: Option<String>,
+        /// Load all the artists in a file, with one id or link per line.
+        #[arg(long, group = "source")]
+        from_file: Option<PathBuf>,
+    },
+    /// Search the Qobuz catalog.
+    Search {
+        #[command(subcommand)]
+        command: SearchCommand,
+    },
+    /// Stop following an artist, given by id, link or name.
+    Unfollow {
+        artist: String,
+        /// Keep the artist's releases and tracks, just stop checking them.
+        #[arg(long)]
+        keep_history: bool,
+    },
+    /// Follow artists from elsewhere.
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+    /// Save data to be imported elsewhere.
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    /// Follow a record label's new releases, given by id or link.
+    FollowLabel { label: String },
+    /// Check for new music from all the artists and labels in the database.
+    Check(CheckArgs),
     /// List all the artists in the database.
     List,
     /// List all the releases for the given artist.
     ListArtist { artist: String },
+    /// List releases that have been announced but can't be streamed yet.
+    Upcoming,
+    /// Recommend artists similar to the ones being followed.
+    Recommend {
+        /// How many recommendations to list.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Load the recommendation with this number instead of listing them.
+        #[arg(long)]
+        load: Option<usize>,
+    },
     /// Generate a playlist with all the latest releases.
-    GenPlaylist,
+    GenPlaylist(PlaylistArgs),
     /// Check for new music and put all the latest releases into a playlist.
-    CheckGen,
+    CheckGen(CheckArgs),
+    /// Log in to Qobuz and remember the auth token.
+    Login {
+        email: String,
+        /// Read the password from stdin instead of prompting for it, for
+        /// scripts.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Forget the remembered auth token.
+    Logout,
+    /// Manage releases that couldn't be loaded when they were found.
+    Pending {
+        #[command(subcommand)]
+        command: PendingCommand,
+    },
+    /// Manage the API response cache (enabled with `QOBUZ_CACHE=1`).
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Manage the database schema.
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum SearchCommand {
+    /// Search for artists by name.
+    Artist { query: String },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ImportCommand {
+    /// Follow the artists in your Qobuz favorites.
+    Favorites {
+        /// Also unfollow artists who aren't favorites any more.
+        #[arg(long)]
+        sync: bool,
+        /// Keep the releases and tracks of artists unfollowed by `--sync`.
+        #[arg(long, requires = "sync")]
+        keep_history: bool,
+    },
+    /// Follow the artists and labels in a file saved by `export follows`.
+    Follows {
+        /// The file to import, or `-` to read from stdin.
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ExportCommand {
+    /// Save the followed artists and labels as JSON.
+    Follows {
+        /// Where to save them. Printed if not given.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum PendingCommand {
+    /// List pending releases and when they'll next be retried.
+    List,
+    /// Try loading pending releases now, or all of them if none are given.
+    Retry {
+        /// Release ids or Qobuz album links.
+        release_ids: Vec<String>,
+    },
+    /// Stop retrying pending releases.
+    Forget {
+        /// Release ids or Qobuz album links.
+        #[arg(required = true)]
+        release_ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum CacheCommand {
+    /// Show how many responses are cached for each endpoint.
+    Stats,
+    /// Remove all cached responses.
+    Clear,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum DbCommand {
+    /// Show the schema version and any pending migrations.
+    Status,
+    /// Apply pending migrations.
+    Migrate,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CheckArgs {
+    /// How many requests to make to Qobuz at once.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+    /// Releases that came out more than this many days before they're found
+    /// count as back catalogue.
+    #[arg(long, default_value_t = 90)]
+    pub max_age_days: u32,
+
+    #[command(flatten)]
+    pub playlist: PlaylistArgs,
+}
+
+impl TryFrom<&CheckArgs> for CheckOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(args: &CheckArgs) -> anyhow::Result<Self> {
+        Ok(Self {
+            concurrency: args.concurrency.into(),
+            new_release_max_age_days: args.max_age_days,
+            exclude: args.playlist.exclude.clone(),
+            pending: PendingRetry::from_env()?,
+        })
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PlaylistArgs {
+    /// Leave out releases of this class. Can be given more than once.
+    #[arg(long, value_enum)]
+    pub exclude: Vec<ReleaseClass>,
+}
+
+impl From<&PlaylistArgs> for PlaylistOptions {
+    fn from(args: &PlaylistArgs) -> Self {
+        Self {
+            exclude: args.exclude.clone(),
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     let subscriber = logging::get_subscriber("qobuz".into(), "error".into(), std::io::stdout);
     logging::init_subscriber(subscriber);
 
     let args = Cli::parse();
 
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Picks an exit code for an error, so scripts can react to API failures
+/// (e.g. an expired token) differently from everything else.
+fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .chain()
+        .find_map(|e| e.downcast_ref::<ApiError>())
+        .map_or(1, ApiError::exit_code)
+}
+
+async fn run(args: Cli) -> anyhow::Result<()> {
+    // The schema is managed without the rest of the app, which would migrate
+    // it straight away.
+    if let Command::Db { command } = args.command {
+        let db = Db::new(&data::db::path_from_env())?;
+        return match command {
+            DbCommand::Status => app::db_status(&db),
+            DbCommand::Migrate => app::db_migrate(&db),
+        };
+    }
+
     let app = App::init()?;
 
     match args.command {
-        Command::Load { artist_id } => app.load_artist(artist_id).await?,
-        Command::Check => app.check_for_new_releases().await?,
+        Command::Load {
+            artist,
+            name,
+            from_file,
+        } => match (artist, name, from_file) {
+            (Some(artist), _, _) if artist == "-" => {
+                app.load_artists(&read_input(Path::new("-"))?).await?;
+            }
+            (Some(artist), _, _) => app.load_artist(QobuzLink::artist_id(&artist)?).await?,
+            (None, Some(name), _) => app.load_artist_by_name(&name, choose_artist).await?,
+            (None, None, Some(path)) => app.load_artists(&read_input(&path)?).await?,
+            (None, None, None) => unreachable!("clap requires one of them"),
+        },
+        Command::Search { command } => match command {
+            SearchCommand::Artist { query } => app.search_artists(&query).await?,
+        },
+        Command::Unfollow {
+            artist,
+            keep_history,
+        } => app.unfollow_artist(&artist, keep_history)?,
+        Command::Import { command } => match command {
+            ImportCommand::Favorites { sync, keep_history } => {
+                app.import_favorites(sync, keep_history).await?;
+            }
+            ImportCommand::Follows { path } => {
+                let follows = FollowList::from_json(&read_input(&path)?)?;
+                app.import_follows(&follows).await?;
+            }
+        },
+        Command::Export { command } => match command {
+            ExportCommand::Follows { output } => {
+                let json = app.export_follows()?.to_json()?;
+                match output {
+                    Some(path) => std::fs::write(&path, json + "\n")
+                        .with_context(|| format!("couldn't write {}", path.display()))?,
+                    None => println!("{json}"),
+                }
+            }
+        },
+        Command::FollowLabel { label } => app.follow_label(QobuzLink::label_id(&label)?).await?,
+        Command::Check(args) => {
+            let options = (&args).try_into()?;
+            app.check_for_new_releases(&options).await?;
+            app.check_labels(&options).await?;
+        }
         Command::List => app.list_artists()?,
-        Command::ListArtist { artist } => app.list_releases_for_artist(artist)?,
-        Command::GenPlaylist => app.gen_playlist().await?,
-        Command::CheckGen => {
-            app.check_for_new_releases().await?;
-            app.gen_playlist().await?;
+        Command::ListArtist { artist } => app.list_releases_for_artist(&artist)?,
+        Command::Upcoming => app.list_upcoming()?,
+        Command::Recommend { limit, load } => match load {
+            Some(rank) => app.load_recommendation(rank).await?,
+            None => app.list_recommendations(limit)?,
+        },
+        Command::GenPlaylist(args) => {
+            app.gen_playlist(&(&args).into()).await?;
+        }
+        Command::CheckGen(args) => {
+            let options = (&args).try_into()?;
+            app.check_for_new_releases(&options).await?;
+            app.check_labels(&options).await?;
+            app.gen_playlist(&(&args.playlist).into()).await?;
+        }
+        Command::Login {
+            email,
+            password_stdin,
+        } => {
+            let password = read_password(password_stdin)?;
+            app.login(&email, &password).await?;
         }
+        Command::Logout => app.logout()?,
+        Command::Pending { command } => match command {
+            PendingCommand::List => app.list_pending(&PendingRetry::from_env()?)?,
+            PendingCommand::Retry { release_ids } => {
+                let release_ids = album_ids(&release_ids)?;
+                app.retry_pending(&release_ids, &CheckOptions::default())
+                    .await?;
+            }
+            PendingCommand::Forget { release_ids } => {
+                app.forget_pending(&album_ids(&release_ids)?)?;
+            }
+        },
+        Command::Cache { command } => match command {
+            CacheCommand::Stats => app.cache_stats()?,
+            CacheCommand::Clear => app.clear_cache()?,
+        },
+        Command::Db { .. } => unreachable!("handled above"),
     }
 
     Ok(())
 }
+
+/// Reads a file, or stdin if the path is `-`.
+fn read_input(path: &Path) -> anyhow::Result<String> {
+    if path.as_os_str() == "-" {
+        return Ok(std::io::read_to_string(std::io::stdin())?);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))
+}
+
+/// Parses release ids that may have been given as album links.
+fn album_ids(releases: &[String]) -> Result<Vec<String>, LinkError> {
+    releases.iter().map(|r| QobuzLink::album_id(r)).collect()
+}
+
+/// Asks the user which of several artists they meant, defaulting to the
+/// first (and best) match.
+fn choose_artist(candidates: &[ArtistSummary]) -> anyhow::Result<usize> {
+    eprintln!("Several artists match:");
+    for (i, artist) in candidates.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, app::describe_artist(artist));
+    }
+    let choice = prompt("Which one? [1] ")?;
+    if choice.trim().is_empty() {
+        return Ok(0);
+    }
+    match choice.trim().parse::<usize>() {
+        Ok(n) if (1..=candidates.len()).contains(&n) => Ok(n - 1),
+        _ => anyhow::bail!("'{choice}' isn't one of the artists"),
+    }
+}
+
+/// Reads the password from stdin, or prompts for it without echoing it.
+fn read_password(password_stdin: bool) -> anyhow::Result<String> {
+    if password_stdin {
+        return prompt("");
+    }
+    rpassword::prompt_password("Password: ").context("couldn't read the password")
+}
+
+/// Asks the user for a line of input.
+fn prompt(message: &str) -> anyhow::Result<String> {
+    eprint!("{message}");
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
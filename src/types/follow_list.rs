@@ -0,0 +1,77 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// A list of followed artists and labels, for moving them between
+/// databases. It's stored as JSON with a format version, so newer builds can
+/// keep reading older exports.
+///
+/// Version 1 only had artists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowList {
+    pub version: u32,
+    pub artists: Vec<FollowedArtist>,
+    #[serde(default)]
+    pub labels: Vec<FollowedLabel>,
+}
+
+/// An artist in a [`FollowList`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowedArtist {
+    pub id: u32,
+    pub name: String,
+    /// When the artist was followed, in local time, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub followed_at: Option<String>,
+}
+
+/// A label in a [`FollowList`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowedLabel {
+    pub id: u64,
+    pub name: String,
+    /// When the label was followed, in local time, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub followed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl FollowList {
+    /// The format version written by this build.
+    pub const VERSION: u32 = 2;
+
+    #[must_use]
+    pub fn new(artists: Vec<FollowedArtist>, labels: Vec<FollowedLabel>) -> Self {
+        Self {
+            version: Self::VERSION,
+            artists,
+            labels,
+        }
+    }
+
+    /// Reads a follow list from JSON.
+    /// # Errors
+    /// Will return `Err` if it isn't a follow list, or was written in a newer
+    /// format than this build knows about.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let Header { version } =
+            serde_json::from_str(json).context("this isn't a list of followed artists")?;
+        if version > Self::VERSION {
+            anyhow::bail!(
+                "the follow list is in format version {version}, but this build only knows up to version {}",
+                Self::VERSION
+            );
+        }
+        serde_json::from_str(json).context("this isn't a list of followed artists")
+    }
+
+    /// Writes the follow list as pretty-printed JSON.
+    /// # Errors
+    /// Will return `Err` if there's an issue.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
@@ -1,3 +1,9 @@
+mod follow_list;
+mod qobuz_link;
+mod release_class;
 mod release_type;
 
+pub use follow_list::{FollowList, FollowedArtist, FollowedLabel};
+pub use qobuz_link::{LinkError, QobuzLink};
+pub use release_class::ReleaseClass;
 pub use release_type::ReleaseType;
@@ -0,0 +1,160 @@
+use reqwest::Url;
+
+const HOSTS: [&str; 4] = [
+    "play.qobuz.com",
+    "open.qobuz.com",
+    "www.qobuz.com",
+    "qobuz.com",
+];
+
+/// Something in the Qobuz catalog, parsed from a web link like
+/// `https://play.qobuz.com/artist/1373166` or
+/// `https://www.qobuz.com/gb-en/album/helium-avralize/na99v5xa7s26a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QobuzLink {
+    Artist(u32),
+    Album(String),
+    Label(u64),
+}
+
+/// Why a link couldn't be used.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LinkError {
+    #[error("'{0}' isn't an id or a Qobuz link")]
+    NotQobuz(String),
+    /// A Qobuz link to something other than an artist, album or label.
+    #[error("Qobuz {kind} links aren't supported: '{link}'")]
+    Unsupported { kind: String, link: String },
+    #[error("couldn't find an id in '{0}'")]
+    MissingId(String),
+    /// A link to the wrong kind of thing, e.g. an album where an artist was
+    /// expected.
+    #[error("expected a link to {expected}, but '{link}' is a link to {found}")]
+    WrongKind {
+        expected: &'static str,
+        found: &'static str,
+        link: String,
+    },
+}
+
+impl QobuzLink {
+    /// Parses a link from the Qobuz web player (`play.qobuz.com`), share
+    /// links (`open.qobuz.com`) or the store (`www.qobuz.com`, where
+    /// artists are under `interpreter`). Query strings and fragments are
+    /// ignored.
+    /// # Errors
+    /// Will return `Err` if it isn't a Qobuz link to an artist, album or
+    /// label.
+    pub fn parse(link: &str) -> Result<Self, LinkError> {
+        let link = link.trim();
+        let not_qobuz = || LinkError::NotQobuz(link.to_string());
+        let url = Url::parse(link).map_err(|_| not_qobuz())?;
+        if !matches!(url.scheme(), "http" | "https")
+            || !url.host_str().is_some_and(|host| HOSTS.contains(&host))
+        {
+            return Err(not_qobuz());
+        }
+
+        let segments = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+        // Store links start with a locale, like `gb-en`.
+        let segments = match segments.as_slice() {
+            [locale, rest @ ..] if is_locale(locale) => rest,
+            segments => segments,
+        };
+        let [kind, rest @ ..] = segments else {
+            return Err(LinkError::MissingId(link.to_string()));
+        };
+        // The id comes last, after the name in store links, so a store link
+        // with only one segment left has a name but no id.
+        let is_store = matches!(url.host_str(), Some("www.qobuz.com" | "qobuz.com"));
+        let ((true, [_, .., id]) | (false, [.., id])) = (is_store, rest) else {
+            return Err(LinkError::MissingId(link.to_string()));
+        };
+
+        match *kind {
+            "artist" | "interpreter" => id
+                .parse()
+                .map(Self::Artist)
+                .map_err(|_| LinkError::MissingId(link.to_string())),
+            "album" => Ok(Self::Album((*id).to_string())),
+            "label" => id
+                .parse()
+                .map(Self::Label)
+                .map_err(|_| LinkError::MissingId(link.to_string())),
+            kind => Err(LinkError::Unsupported {
+                kind: kind.to_string(),
+                link: link.to_string(),
+            }),
+        }
+    }
+
+    /// Parses an artist id, given on its own or as a link.
+    /// # Errors
+    /// Will return `Err` if it's neither an id nor a link to an artist.
+    pub fn artist_id(artist: &str) -> Result<u32, LinkError> {
+        if let Ok(artist_id) = artist.trim().parse() {
+            return Ok(artist_id);
+        }
+        match Self::parse(artist)? {
+            Self::Artist(artist_id) => Ok(artist_id),
+            link @ (Self::Album(_) | Self::Label(_)) => Err(link.wrong_kind("an artist", artist)),
+        }
+    }
+
+    /// Parses an album (release) id, given on its own or as a link.
+    /// # Errors
+    /// Will return `Err` if it's a link to something other than an album.
+    pub fn album_id(album: &str) -> Result<String, LinkError> {
+        let album = album.trim();
+        if !album.contains('/') {
+            return Ok(album.to_string());
+        }
+        match Self::parse(album)? {
+            Self::Album(album_id) => Ok(album_id),
+            link @ (Self::Artist(_) | Self::Label(_)) => Err(link.wrong_kind("an album", album)),
+        }
+    }
+
+    /// Parses a label id, given on its own or as a link.
+    /// # Errors
+    /// Will return `Err` if it's neither an id nor a link to a label.
+    pub fn label_id(label: &str) -> Result<u64, LinkError> {
+        if let Ok(label_id) = label.trim().parse() {
+            return Ok(label_id);
+        }
+        match Self::parse(label)? {
+            Self::Label(label_id) => Ok(label_id),
+            link @ (Self::Artist(_) | Self::Album(_)) => Err(link.wrong_kind("a label", label)),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Artist(_) => "an artist",
+            Self::Album(_) => "an album",
+            Self::Label(_) => "a label",
+        }
+    }
+
+    fn wrong_kind(&self, expected: &'static str, link: &str) -> LinkError {
+        LinkError::WrongKind {
+            expected,
+            found: self.kind(),
+            link: link.trim().to_string(),
+        }
+    }
+}
+
+/// Whether a path segment is a store locale, like `gb-en` or `fr-fr`.
+fn is_locale(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    bytes.len() == 5
+        && bytes[2] == b'-'
+        && bytes[..2].iter().all(u8::is_ascii_lowercase)
+        && bytes[3..].iter().all(u8::is_ascii_lowercase)
+}
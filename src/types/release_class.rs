@@ -0,0 +1,53 @@
+use chrono::NaiveDate;
+use rusqlite::{
+    ToSql,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+};
+
+/// Whether a release found during a check actually came out recently, or is
+/// an old release that only just showed up on Qobuz.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, clap::ValueEnum)]
+pub enum ReleaseClass {
+    NewRelease,
+    BackCatalogue,
+}
+
+impl ReleaseClass {
+    /// Classifies a release by how long before `today` it came out. Releases
+    /// without a (valid) release date are given the benefit of the doubt.
+    #[must_use]
+    pub fn classify(release_date: Option<&str>, today: NaiveDate, max_age_days: u32) -> Self {
+        let release_date =
+            release_date.and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+        match release_date {
+            Some(date) if (today - date).num_days() > i64::from(max_age_days) => {
+                Self::BackCatalogue
+            }
+            _ => Self::NewRelease,
+        }
+    }
+
+    #[must_use]
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::NewRelease => "NewRelease",
+            Self::BackCatalogue => "BackCatalogue",
+        }
+    }
+}
+
+impl ToSql for ReleaseClass {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_str()))
+    }
+}
+
+impl FromSql for ReleaseClass {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "NewRelease" => Ok(Self::NewRelease),
+            "BackCatalogue" => Ok(Self::BackCatalogue),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
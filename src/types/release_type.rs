@@ -17,6 +17,21 @@ pub enum ReleaseType {
 }
 
 impl ReleaseType {
+    /// The name Qobuz uses for this release type in requests.
+    #[must_use]
+    pub fn to_api_str(self) -> &'static str {
+        match self {
+            Self::Album => "album",
+            Self::Compilation => "compilation",
+            Self::Download => "download",
+            Self::EpSingle => "epSingle",
+            Self::Live => "live",
+            Self::AwardedReleases => "awardedReleases",
+            Self::Other => "other",
+        }
+    }
+
+    #[must_use]
     pub fn to_str(self) -> &'static str {
         match self {
             Self::Album => "Album",
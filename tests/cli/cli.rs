@@ -1,13 +1,28 @@
+use wiremock::{Mock, ResponseTemplate, matchers};
+
 use crate::helpers;
 
 const HELP_MSG: &str = "Usage: qobuz <COMMAND>
 
 Commands:
   load          Load an artist's releases into the database
-  check         Check for new music from all the artists in the database
+  search        Search the Qobuz catalog
+  unfollow      Stop following an artist, given by id, link or name
+  import        Follow artists from elsewhere
+  export        Save data to be imported elsewhere
+  follow-label  Follow a record label's new releases, given by id or link
+  check         Check for new music from all the artists and labels in the database
   list          List all the artists in the database
+  list-artist   List all the releases for the given artist
+  upcoming      List releases that have been announced but can't be streamed yet
+  recommend     Recommend artists similar to the ones being followed
   gen-playlist  Generate a playlist with all the latest releases
   check-gen     Check for new music and put all the latest releases into a playlist
+  login         Log in to Qobuz and remember the auth token
+  logout        Forget the remembered auth token
+  pending       Manage releases that couldn't be loaded when they were found
+  cache         Manage the API response cache (enabled with `QOBUZ_CACHE=1`)
+  db            Manage the database schema
   help          Print this message or the help of the given subcommand(s)
 
 Options:
@@ -25,7 +40,7 @@ fn help() {
 }
 
 const LOAD_AND_LIST_1: &str = "Loading data for \'AVRALIZE\'
-Loaded 7 releases
+Loaded 9 releases
 ";
 
 const LOAD_AND_LIST_2: &str = "AVRALIZE
@@ -54,10 +69,82 @@ async fn list() {
         .stdout(LOAD_AND_LIST_2);
 }
 
+#[tokio::test]
+async fn unfollow() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["unfollow", "avralize"])
+        .assert()
+        .success()
+        .stdout("Unfollowed 'AVRALIZE', removing 9 releases and 0 tracks\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone()).arg("list").assert().stdout("");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let output = cmd
+        .envs(test.vars.clone())
+        .args(["unfollow", "13925362"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Error: '13925362' isn't a followed artist"));
+}
+
+#[tokio::test]
+async fn rejects_non_finite_rate_limits() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let output = cmd
+        .envs(test.vars.clone())
+        .env("QOBUZ_RATE_LIMIT", "nan")
+        .args(["load", "13925362"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(
+            "Error: QOBUZ_RATE_LIMIT must be a number of requests per second, not 'nan'"
+        )
+    );
+}
+
+#[tokio::test]
+async fn rejects_tiny_rate_limits() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let output = cmd
+        .envs(test.vars.clone())
+        .env("QOBUZ_RATE_LIMIT", "1e-300")
+        .args(["load", "13925362"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with(
+        "Error: QOBUZ_RATE_LIMIT must be 0 or at least 0.001 requests per second, not '1e-300'"
+    ));
+}
+
 const CHECK_1: &str = "Checking 1 artists
 
-Found 1 new release for AVRALIZE
-  • helium
+Found 1 back catalogue release for AVRALIZE
+  • helium (2025-05-09)
 ";
 
 #[tokio::test]
@@ -89,6 +176,250 @@ async fn check() {
         .stdout(CHECK_1);
 }
 
+const LIST_ARTIST: &str = "Releases for AVRALIZE
+  Album
+    - FREAKS (2024-03-22) · Arising Empire · Metal · 8 tracks · 34:48 · 24-bit/44.1 kHz · explicit
+  EpSingle
+    - LOST (2022-11-04) · Arising Empire · Metal · 1 track · 3:09 · 16-bit/44.1 kHz
+    - SILENCE (2023-03-17) · Arising Empire · Metal · 1 track · 3:21 · 16-bit/44.1 kHz
+    - STAB BY STAB (2023-08-11) · Arising Empire · Metal · 1 track · 2:58 · 16-bit/44.1 kHz
+    - LOTUS (2024-01-16) · Arising Empire · Metal · 5 tracks · 20:59 · 16-bit/44.1 kHz · explicit
+    - HIGHER (2024-02-09) · Arising Empire · Metal · 6 tracks · 25:11 · 24-bit/44.1 kHz · explicit
+    - OVERDOSE (2024-03-01) · Arising Empire · Metal · 7 tracks · 30:32 · 24-bit/44.1 kHz · explicit
+    - upside down (2024-09-20) · Arising Empire · Metal · 1 track · 4:04 · 24-bit/44.1 kHz
+    - wanderlust (2025-01-16) · Arising Empire · Metal · 2 tracks · 6:56 · 24-bit/44.1 kHz
+    - helium (2025-05-09) · Arising Empire · Metal · 3 tracks · 10:31 · 24-bit/44.1 kHz
+";
+
+#[tokio::test]
+async fn list_artist() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone()).arg("check").assert().success();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["list-artist", "AVRALIZE"])
+        .assert()
+        .stdout(LIST_ARTIST);
+}
+
+#[tokio::test]
+async fn upcoming() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .arg("upcoming")
+        .assert()
+        .stdout("No upcoming releases\n");
+}
+
+#[tokio::test]
+async fn pending() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["pending", "list"])
+        .assert()
+        .stdout("No pending releases\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let assert = cmd
+        .envs(test.vars.clone())
+        .args(["pending", "retry", "na99v5xa7s26a"])
+        .assert()
+        .code(1);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.starts_with("Error: release na99v5xa7s26a isn't pending"));
+}
+
+#[tokio::test]
+async fn exclude_back_catalogue() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    // helium is too old to be a new release.
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["check-gen", "--exclude", "back-catalogue"])
+        .assert()
+        .stdout(
+            "Checking 1 artists\n\nNo new music found\nNo new tracks. Skipping playlist creation\n",
+        );
+}
+
+const SEARCH_ARTIST: &str = "13925362  AVRALIZE · 12 albums · Metal
+20512877  Avralize Tribute Band · 1 album
+";
+
+#[tokio::test]
+async fn search_and_load_by_name() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["search", "artist", "avralize"])
+        .assert()
+        .success()
+        .stdout(SEARCH_ARTIST);
+
+    // Only one of them is called AVRALIZE, so there's nothing to ask.
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "--name", "Avralize"])
+        .assert()
+        .success()
+        .stdout(LOAD_AND_LIST_1);
+}
+
+#[tokio::test]
+async fn import_favorites() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["import", "favorites"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{LOAD_AND_LIST_1}Imported 1 favorite artist, 0 already followed, 0 failed\n"
+        ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["import", "favorites", "--sync"])
+        .assert()
+        .success()
+        .stdout("Imported 0 favorite artists, 1 already followed, 0 failed\n");
+}
+
+#[tokio::test]
+async fn load_from_stdin() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "-"])
+        .write_stdin("# AVRALIZE\nhttps://play.qobuz.com/artist/13925362\n13925362\n")
+        .assert()
+        .success()
+        .stdout(format!(
+            "{LOAD_AND_LIST_1}Loaded 1 artist, 1 already present, 0 failed\n"
+        ));
+}
+
+#[tokio::test]
+async fn export_and_import_follows() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let output = cmd
+        .envs(test.vars.clone())
+        .args(["export", "follows"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let export = String::from_utf8(output.stdout).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&export).unwrap();
+    assert_eq!(json["version"], 2);
+    assert_eq!(json["artists"][0]["id"], 13_925_362);
+    assert_eq!(json["artists"][0]["name"], "AVRALIZE");
+    assert!(json["artists"][0]["followed_at"].is_string());
+    assert_eq!(json["labels"], serde_json::json!([]));
+
+    // Import into a fresh database, whose artist page is the updated one.
+    let import = helpers::Test::init().await;
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(import.vars.clone())
+        .args(["import", "follows", "-"])
+        .write_stdin(export.clone())
+        .assert()
+        .success()
+        .stdout(format!(
+            "{LOAD_AND_LIST_1}Imported 1 artist, 0 already followed, 0 conflicts, 0 failed\n"
+        ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(import.vars.clone())
+        .args(["export", "follows"])
+        .assert()
+        .success()
+        .stdout(export);
+}
+
+const LABEL_PAGE_0_RESPONSE: &str = include_str!("responses/label_page_0.json");
+const LABEL_PAGE_1_RESPONSE: &str = include_str!("responses/label_page_1.json");
+
+const CHECK_LABELS: &str = "Checking 0 artists
+
+No new music found
+
+Checking 1 labels
+
+Found 1 back catalogue release for Arising Empire
+  • helium (2025-05-09)
+";
+
+#[tokio::test]
+async fn follow_label() {
+    let test = helpers::Test::init().await;
+    // The label gets a new release after it's followed.
+    for body in [LABEL_PAGE_0_RESPONSE, LABEL_PAGE_1_RESPONSE] {
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path("/label/get"))
+            .and(matchers::query_param("label_id", "1130922"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .up_to_n_times(1)
+            .mount(&test.mock_server)
+            .await;
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args([
+            "follow-label",
+            "https://www.qobuz.com/gb-en/label/arising-empire-4/download-streaming-albums/1130922",
+        ])
+        .assert()
+        .success()
+        .stdout("Loading data for label 'Arising Empire'\nLoaded 1 releases\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .arg("check")
+        .assert()
+        .success()
+        .stdout(CHECK_LABELS);
+}
+
 #[tokio::test]
 async fn gen_playlist() {
     let test = helpers::Test::init().await;
@@ -100,6 +431,13 @@ async fn gen_playlist() {
         .assert()
         .stdout(LOAD_AND_LIST_1);
 
+    // Find a new release to put in the playlist.
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .arg("check")
+        .assert()
+        .stdout(CHECK_1);
+
     // Generate a playlist.
     let now = chrono::Local::now().date_naive().to_string();
     let expected_stdout = format!("Created playlist: {now}\n");
@@ -109,3 +447,206 @@ async fn gen_playlist() {
         .assert()
         .stdout(expected_stdout);
 }
+
+#[tokio::test]
+async fn api_errors_have_exit_codes() {
+    let test = helpers::Test::init().await;
+
+    // The mock server doesn't know about this artist.
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["load", "1"])
+        .assert()
+        .code(4);
+}
+
+#[tokio::test]
+async fn login_and_logout() {
+    let test = helpers::Test::init().await;
+    Mock::given(matchers::method("GET"))
+        .and(matchers::path("/user/login"))
+        .and(matchers::query_param("email", "me@example.com"))
+        // md5("hunter2")
+        .and(matchers::query_param(
+            "password",
+            "2ab96390c7dbe3439de74d0c9b0b1767",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "user": { "id": 2_596_068 },
+            "user_auth_token": "stored-token",
+        })))
+        .mount(&test.mock_server)
+        .await;
+
+    let mut vars = test.vars.clone();
+    vars.remove("QOBUZ_AUTH_TOKEN");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars.clone())
+        .args(["login", "me@example.com", "--password-stdin"])
+        .write_stdin("hunter2\n")
+        .assert()
+        .success()
+        .stdout("Logged in as user 2596068\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = std::fs::metadata(&vars["QOBUZ_DB_PATH"]).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+
+    // The stored token is used from now on.
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.env_remove("QOBUZ_AUTH_TOKEN")
+        .envs(vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .success();
+    let requests = test.mock_server.received_requests().await.unwrap();
+    let artist_page_request = requests
+        .iter()
+        .find(|r| r.url.path() == "/artist/page")
+        .unwrap();
+    assert_eq!(
+        artist_page_request
+            .headers
+            .get("X-User-Auth-Token")
+            .unwrap(),
+        "stored-token"
+    );
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars.clone())
+        .arg("logout")
+        .assert()
+        .stdout("Logged out\n");
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars)
+        .arg("logout")
+        .assert()
+        .stdout("Not logged in\n");
+}
+
+#[tokio::test]
+async fn cache_stats_and_clear() {
+    let test = helpers::Test::init().await;
+    let mut vars = test.vars.clone();
+    vars.insert("QOBUZ_CACHE", "1".to_string());
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars.clone())
+        .arg("cache")
+        .arg("stats")
+        .assert()
+        .stdout("The cache is empty\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars.clone())
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    let output = cmd
+        .envs(vars.clone())
+        .args(["cache", "stats"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("artist/getReleasesList: 1 responses"));
+    assert!(stdout.contains("artist/page: 1 responses"));
+    assert!(stdout.contains("Total: 2 responses"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars)
+        .args(["cache", "clear"])
+        .assert()
+        .stdout("Cleared 2 cached responses\n");
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let test = helpers::Test::init().await;
+    let cassette_dir = std::env::temp_dir().join(helpers::mk_rand_str());
+    let cassette_dir = cassette_dir.to_str().unwrap().to_string();
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .env("QOBUZ_RECORD_DIR", &cassette_dir)
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    // Replay into a fresh database without a server to talk to.
+    let db_path = helpers::mk_db_name();
+    let mut vars = test.vars.clone();
+    vars.insert("QOBUZ_DB_PATH", db_path.clone());
+    vars.insert("QOBUZ_API_BASE", "http://127.0.0.1:9".to_string());
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(vars)
+        .env("QOBUZ_REPLAY_DIR", &cassette_dir)
+        .args(["load", "13925362"])
+        .assert()
+        .stdout(LOAD_AND_LIST_1);
+
+    _ = std::fs::remove_file(db_path);
+    _ = std::fs::remove_dir_all(cassette_dir);
+}
+
+const DB_STATUS_NEW: &str = "Schema version 0 of 11
+Pending migrations:
+  1: initial schema
+  2: credentials
+  3: http cache
+  4: release details
+  5: release classes
+  6: upcoming releases
+  7: pending releases
+  8: unfollowed artists
+  9: follow dates
+  10: labels
+  11: similar artists
+";
+
+const DB_MIGRATE: &str = "Applied migration 1: initial schema
+Applied migration 2: credentials
+Applied migration 3: http cache
+Applied migration 4: release details
+Applied migration 5: release classes
+Applied migration 6: upcoming releases
+Applied migration 7: pending releases
+Applied migration 8: unfollowed artists
+Applied migration 9: follow dates
+Applied migration 10: labels
+Applied migration 11: similar artists
+Schema version 11 is up to date
+";
+
+#[tokio::test]
+async fn db_status_and_migrate() {
+    let test = helpers::Test::init().await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["db", "status"])
+        .assert()
+        .stdout(DB_STATUS_NEW);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["db", "migrate"])
+        .assert()
+        .stdout(DB_MIGRATE);
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["db", "status"])
+        .assert()
+        .stdout("Schema version 11 (up to date)\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("qobuz").unwrap();
+    cmd.envs(test.vars.clone())
+        .args(["db", "migrate"])
+        .assert()
+        .stdout("Schema version 11 is already up to date\n");
+}
@@ -5,13 +5,17 @@ use rand::Rng;
 
 const ARTIST_PAGE_0_RESPONSE: &str = include_str!("responses/artist_page_0.json");
 const ARTIST_PAGE_1_RESPONSE: &str = include_str!("responses/artist_page_1.json");
+const ARTIST_RELEASES_EP_SINGLE_RESPONSE: &str =
+    include_str!("responses/artist_releases_ep_single.json");
 const ALBUM_PAGE_RESPONSE: &str = include_str!("responses/album_page.json");
+const SEARCH_ARTISTS_RESPONSE: &str = include_str!("responses/search_artists.json");
+const FAVORITE_ARTISTS_RESPONSE: &str = include_str!("responses/favorite_artists.json");
 const PLAYLIST_CREATE_RESPONSE: &str = include_str!("responses/playlist_create.json");
 
 pub struct Test {
     db_path: String,
     pub vars: HashMap<&'static str, String>,
-    _mock_server: MockServer,
+    pub mock_server: MockServer,
 }
 
 impl Test {
@@ -28,7 +32,7 @@ impl Test {
                 ("QOBUZ_APP_ID", rand_str),
                 ("QOBUZ_API_BASE", api_base),
             ]),
-            _mock_server: mock_server,
+            mock_server,
         }
     }
 }
@@ -64,6 +68,18 @@ async fn init_server() -> MockServer {
         .mount(&mock_server)
         .await;
 
+    // GET Artist Releases past the ones on the artist page.
+    let artist_releases_response_body = load_json_response(ARTIST_RELEASES_EP_SINGLE_RESPONSE);
+    let artist_releases_response =
+        ResponseTemplate::new(200).set_body_json(artist_releases_response_body);
+    Mock::given(matchers::method("GET"))
+        .and(matchers::path("/artist/getReleasesList"))
+        .and(matchers::query_param("artist_id", "13925362"))
+        .and(matchers::query_param("release_type", "epSingle"))
+        .respond_with(artist_releases_response)
+        .mount(&mock_server)
+        .await;
+
     // GET Album Page. Responds once.
     let album_page_response_body = load_json_response(ALBUM_PAGE_RESPONSE);
     let album_page_response = ResponseTemplate::new(200).set_body_json(album_page_response_body);
@@ -75,6 +91,26 @@ async fn init_server() -> MockServer {
         .mount(&mock_server)
         .await;
 
+    // GET Catalog Search for artists.
+    let search_response_body = load_json_response(SEARCH_ARTISTS_RESPONSE);
+    let search_response = ResponseTemplate::new(200).set_body_json(search_response_body);
+    Mock::given(matchers::method("GET"))
+        .and(matchers::path("/catalog/search"))
+        .and(matchers::query_param("type", "artists"))
+        .respond_with(search_response)
+        .mount(&mock_server)
+        .await;
+
+    // GET the user's favorite artists.
+    let favorites_response_body = load_json_response(FAVORITE_ARTISTS_RESPONSE);
+    let favorites_response = ResponseTemplate::new(200).set_body_json(favorites_response_body);
+    Mock::given(matchers::method("GET"))
+        .and(matchers::path("/favorite/getUserFavorites"))
+        .and(matchers::query_param("type", "artists"))
+        .respond_with(favorites_response)
+        .mount(&mock_server)
+        .await;
+
     // POST Playlist Create. Responds once.
     let playlist_create_response_body = load_json_response(PLAYLIST_CREATE_RESPONSE);
     let playlist_create_response =
@@ -90,6 +126,18 @@ async fn init_server() -> MockServer {
         .mount(&mock_server)
         .await;
 
+    // POST Playlist Add Tracks. Responds once.
+    let playlist_add_tracks_response_body = load_json_response(PLAYLIST_CREATE_RESPONSE);
+    let playlist_add_tracks_response =
+        ResponseTemplate::new(200).set_body_json(playlist_add_tracks_response_body);
+    Mock::given(matchers::method("POST"))
+        .and(matchers::path("/playlist/addTracks"))
+        .and(matchers::body_string_contains("playlist_id=32500402"))
+        .respond_with(playlist_add_tracks_response)
+        .up_to_n_times(1)
+        .mount(&mock_server)
+        .await;
+
     mock_server
 }
 
@@ -0,0 +1,997 @@
+use std::time::Duration;
+
+use qobuz::{
+    App, Db,
+    api::{
+        InMemoryCatalog,
+        models::{AlbumPage, ArtistSummary},
+    },
+    app::{CheckOptions, PendingRetry, PlaylistOptions},
+    data::{artists, labels, pending, releases, similar_artists, tracks},
+    types::{FollowList, ReleaseClass, ReleaseType},
+};
+
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers};
+
+use crate::helpers::{self, ARTIST_ID, NEW_RELEASE_ID};
+
+#[tokio::test]
+async fn check_finds_new_releases() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].artist.name, "AVRALIZE");
+    let release_ids = found[0]
+        .releases
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(release_ids, [NEW_RELEASE_ID]);
+    assert_eq!(
+        releases::get_all_for_artist(app.db(), ARTIST_ID)
+            .unwrap()
+            .len(),
+        8
+    );
+
+    // Nothing changed since the last check.
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+}
+
+#[tokio::test]
+async fn stores_release_details() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+
+    let all_releases = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    let details = |id: &str| {
+        all_releases
+            .iter()
+            .find(|r| r.id == id)
+            .map(|r| r.details.clone())
+            .unwrap()
+    };
+
+    // Loaded releases have what the artist page knows about them.
+    let freaks = details("hw971g1ngzxca");
+    assert_eq!(freaks.release_date.as_deref(), Some("2024-03-22"));
+    assert_eq!(freaks.label_name.as_deref(), Some("Arising Empire"));
+    assert_eq!(freaks.genre_name.as_deref(), Some("Metal"));
+    assert_eq!(freaks.tracks_count, Some(8));
+    assert_eq!(freaks.parental_warning, Some(true));
+    assert_eq!(freaks.upc, None);
+
+    // New releases are filled in from the release itself.
+    let helium = details(NEW_RELEASE_ID);
+    assert_eq!(helium.release_date.as_deref(), Some("2025-05-09"));
+    assert_eq!(helium.released_at, Some(1_746_741_600));
+    assert_eq!(helium.upc.as_deref(), Some("4069977057550"));
+    assert_eq!(helium.maximum_bit_depth, Some(24));
+    assert_eq!(helium.maximum_sampling_rate, Some(44.1));
+    assert_eq!(helium.duration, Some(631));
+}
+
+#[tokio::test]
+async fn classifies_releases_by_age() {
+    // helium came out on 2025-05-09.
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        found[0].releases[0].class,
+        Some(ReleaseClass::BackCatalogue)
+    );
+
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    let found = app
+        .check_for_new_releases(&CheckOptions {
+            new_release_max_age_days: u32::MAX,
+            ..CheckOptions::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(found[0].releases[0].class, Some(ReleaseClass::NewRelease));
+}
+
+#[tokio::test]
+async fn excludes_back_catalogue() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    let exclude = vec![ReleaseClass::BackCatalogue];
+
+    let found = app
+        .check_for_new_releases(&CheckOptions {
+            exclude: exclude.clone(),
+            ..CheckOptions::default()
+        })
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+
+    // It's still stored, so it isn't found again.
+    let stored = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    assert!(stored.iter().any(|r| r.id == NEW_RELEASE_ID));
+
+    let playlist = app
+        .gen_playlist(&PlaylistOptions { exclude })
+        .await
+        .unwrap();
+    assert!(playlist.is_none());
+    assert!(
+        app.gen_playlist(&PlaylistOptions::default())
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn promotes_upcoming_releases() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    // The new release is announced, but can't be streamed yet.
+    let mut album = helpers::album_page();
+    album.streamable = Some(false);
+    album.release_date_stream = Some("2999-01-01".to_string());
+    app.catalog().add_release(album);
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+    let stored = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    let upcoming = stored.iter().find(|r| r.id == NEW_RELEASE_ID).unwrap();
+    assert!(upcoming.upcoming);
+    assert!(tracks::get_latest(app.db(), &[]).unwrap().is_empty());
+
+    // Now it can be.
+    app.catalog().add_release(helpers::album_page());
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    let release_ids = found[0]
+        .releases
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(release_ids, [NEW_RELEASE_ID]);
+    assert!(!found[0].releases[0].upcoming);
+    assert_eq!(tracks::get_latest(app.db(), &[]).unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn waits_for_releases_past_their_date_to_be_streamable() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    // The stream date has passed, but the release still can't be streamed.
+    let mut album = helpers::album_page();
+    album.streamable = Some(false);
+    album.release_date_stream = Some("2000-01-01".to_string());
+    app.catalog().add_release(album);
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+    assert!(tracks::get_latest(app.db(), &[]).unwrap().is_empty());
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        1
+    );
+}
+
+#[tokio::test]
+async fn waits_for_unstreamable_releases_without_a_date() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    let mut album = helpers::album_page();
+    album.streamable = Some(false);
+    album.release_date_stream = None;
+    album.streamable_at = None;
+    app.catalog().add_release(album);
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+    assert!(tracks::get_latest(app.db(), &[]).unwrap().is_empty());
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        1
+    );
+
+    // Once it can be streamed, it's picked up.
+    app.catalog().add_release(helpers::album_page());
+    let no_wait = CheckOptions {
+        pending: PendingRetry {
+            base_delay: Duration::ZERO,
+            ..PendingRetry::default()
+        },
+        ..CheckOptions::default()
+    };
+    let found = app.check_for_new_releases(&no_wait).await.unwrap();
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+    assert_eq!(tracks::get_latest(app.db(), &[]).unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn retries_releases_without_tracks() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    // The new release shows up before its tracks do.
+    let mut album = helpers::album_page();
+    album.tracks.items.clear();
+    app.catalog().add_release(album);
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        1
+    );
+
+    // It's not due for another retry yet.
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        1
+    );
+
+    // Unless there's no waiting between retries.
+    let no_wait = CheckOptions {
+        pending: PendingRetry {
+            base_delay: Duration::ZERO,
+            ..PendingRetry::default()
+        },
+        ..CheckOptions::default()
+    };
+    app.check_for_new_releases(&no_wait).await.unwrap();
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        2
+    );
+
+    // The tracks turn up, and retrying by hand loads them.
+    app.catalog().add_release(helpers::album_page());
+    let found = app
+        .retry_pending(&[], &CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+    assert!(pending::get_all(app.db()).unwrap().is_empty());
+    assert_eq!(tracks::get_latest(app.db(), &[]).unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn failed_checks_leave_nothing_behind() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    // Fail part way through storing the artist's new release.
+    app.db()
+        .conn
+        .execute_batch(
+            "create temp trigger fail_tracks before insert on tracks_2_releases
+            begin
+                select raise(abort, 'disk on fire');
+            end;",
+        )
+        .unwrap();
+
+    let result = app.check_for_new_releases(&CheckOptions::default()).await;
+
+    assert!(result.is_err());
+    let stored = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    assert!(stored.iter().all(|r| r.id != NEW_RELEASE_ID));
+    let tracks_count: u32 = app
+        .db()
+        .conn
+        .query_row("select count(*) from tracks", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(tracks_count, 0);
+
+    // So the release is found again once things are working.
+    app.db()
+        .conn
+        .execute_batch("drop trigger fail_tracks;")
+        .unwrap();
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+}
+
+#[tokio::test]
+async fn failed_fetches_keep_earlier_artists() {
+    let mock_server = MockServer::start().await;
+    for (artist_id, release_id, status) in
+        [(ARTIST_ID, "first", 200), (ARTIST_ID + 1, "second", 500)]
+    {
+        let mut page = helpers::artist_page_json();
+        page["id"] = artist_id.into();
+        let mut release = page["releases"][0]["items"][0].clone();
+        release["id"] = release_id.into();
+        page["releases"] = serde_json::json!([
+            { "type": "album", "has_more": false, "items": [release] }
+        ]);
+        Mock::given(matchers::path("/artist/page"))
+            .and(matchers::query_param("artist_id", artist_id.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page))
+            .mount(&mock_server)
+            .await;
+        Mock::given(matchers::path("/album/get"))
+            .and(matchers::query_param("album_id", release_id))
+            .respond_with(ResponseTemplate::new(status).set_body_json(helpers::album_page_json()))
+            .mount(&mock_server)
+            .await;
+    }
+    let app = App::new(Db::new(":memory:").unwrap(), helpers::mk_api(&mock_server)).unwrap();
+    for artist_id in [ARTIST_ID, ARTIST_ID + 1] {
+        artists::insert(
+            app.db(),
+            &artists::Artist {
+                id: artist_id,
+                name: artist_id.to_string(),
+            },
+        )
+        .unwrap();
+    }
+
+    let result = app.check_for_new_releases(&CheckOptions::default()).await;
+
+    // The second artist's release couldn't be fetched, but the first
+    // artist's was already stored.
+    assert!(result.is_err());
+    let first = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].id, "first");
+    assert!(
+        releases::get_all_for_artist(app.db(), ARTIST_ID + 1)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn gen_playlist_uses_new_tracks() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    let track_ids = tracks::get_latest(app.db(), &[]).unwrap();
+
+    let playlist = app
+        .gen_playlist(&PlaylistOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let created = app.catalog().playlists();
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].id, playlist.id);
+    assert_eq!(created[0].track_ids, track_ids);
+    assert_eq!(track_ids.len(), 3);
+}
+
+fn count(app: &qobuz::App<qobuz::api::InMemoryCatalog>, table: &str) -> u32 {
+    app.db()
+        .conn
+        .query_row(&format!("select count(*) from {table}"), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn unfollow_keeps_shared_releases() {
+    let app = helpers::mk_app_with_artists(2);
+    for i in 0..2 {
+        app.load_artist(ARTIST_ID + i).await.unwrap();
+    }
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    let num_tracks = count(&app, "tracks");
+    assert!(num_tracks > 0);
+
+    // Both artists have the same releases, so nothing is deleted yet.
+    app.unfollow_artist("AVRALIZE", false).unwrap();
+    assert_eq!(artists::get_by_id(app.db(), ARTIST_ID).unwrap(), None);
+    assert_eq!(count(&app, "releases"), 8);
+    assert_eq!(count(&app, "tracks"), num_tracks);
+
+    app.unfollow_artist(&(ARTIST_ID + 1).to_string(), false)
+        .unwrap();
+    for table in ["releases", "release_details", "tracks", "tracks_2_releases"] {
+        assert_eq!(count(&app, table), 0, "{table}");
+    }
+
+    assert!(app.unfollow_artist("AVRALIZE", false).is_err());
+}
+
+#[tokio::test]
+async fn unfollow_matches_whole_names() {
+    let catalog = InMemoryCatalog::new();
+    catalog.add_artist_page(helpers::artist_page_with_similar(1, "AXB", &[]));
+    catalog.add_artist_page(helpers::artist_page_with_similar(2, "A_B", &[]));
+    let app = App::new(Db::new(":memory:").unwrap(), catalog).unwrap();
+    app.load_artist(1).await.unwrap();
+
+    // Neither `_` nor `%` match other characters.
+    assert!(app.unfollow_artist("A_B", false).is_err());
+    assert!(app.unfollow_artist("A%", false).is_err());
+    assert!(artists::is_followed(app.db(), 1).unwrap());
+
+    app.load_artist(2).await.unwrap();
+    app.unfollow_artist("a_b", false).unwrap();
+    assert!(artists::is_followed(app.db(), 1).unwrap());
+    assert!(!artists::is_followed(app.db(), 2).unwrap());
+}
+
+#[tokio::test]
+async fn recommends_similar_artists() {
+    const A: u32 = 1;
+    const B: u32 = 2;
+    const X: u32 = 10;
+    const Y: u32 = 11;
+    const Z: u32 = 12;
+
+    let catalog = InMemoryCatalog::new();
+    catalog.add_artist_page(helpers::artist_page_with_similar(
+        A,
+        "A",
+        &[(X, "X"), (Y, "Y"), (B, "B")],
+    ));
+    catalog.add_artist_page(helpers::artist_page_with_similar(
+        A,
+        "A",
+        &[(X, "X"), (Z, "Z")],
+    ));
+    catalog.add_artist_page(helpers::artist_page_with_similar(
+        B,
+        "B",
+        &[(X, "X"), (A, "A")],
+    ));
+    catalog.add_artist_page(helpers::artist_page_with_similar(X, "X", &[]));
+    let app = App::new(Db::new(":memory:").unwrap(), catalog).unwrap();
+    let recommended = |app: &App<InMemoryCatalog>| {
+        similar_artists::get_recommendations(app.db())
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.artist.id, r.similar_to))
+            .collect::<Vec<_>>()
+    };
+
+    app.load_artist(A).await.unwrap();
+    app.load_artist(B).await.unwrap();
+    // Followed artists aren't recommended.
+    assert_eq!(
+        recommended(&app),
+        [(X, vec!["A".into(), "B".into()]), (Y, vec!["A".into()])]
+    );
+
+    // Checks pick up the latest similar artists.
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        recommended(&app),
+        [(X, vec!["A".into(), "B".into()]), (Z, vec!["A".into()])]
+    );
+
+    app.load_recommendation(1).await.unwrap();
+    assert!(artists::is_followed(app.db(), X).unwrap());
+    assert_eq!(recommended(&app), [(Z, vec!["A".into()])]);
+    assert!(app.load_recommendation(2).await.is_err());
+
+    // Unfollowed artists take their similar artists with them, but can be
+    // recommended again.
+    app.unfollow_artist("A", false).unwrap();
+    assert_eq!(recommended(&app), [(A, vec!["B".into()])]);
+}
+
+#[tokio::test]
+async fn unfollow_can_keep_history() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    app.unfollow_artist("avralize", true).unwrap();
+
+    assert!(artists::get_all(app.db()).unwrap().is_empty());
+    assert_eq!(
+        releases::get_all_for_artist(app.db(), ARTIST_ID)
+            .unwrap()
+            .len(),
+        7
+    );
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+
+    // Loading them again picks up where they left off.
+    app.load_artist(ARTIST_ID).await.unwrap();
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+}
+
+#[tokio::test]
+async fn unfollowed_history_can_be_removed_later() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.unfollow_artist("avralize", true).unwrap();
+    assert!(app.unfollow_artist("avralize", true).is_err());
+
+    app.unfollow_artist("avralize", false).unwrap();
+
+    assert_eq!(artists::get_by_id(app.db(), ARTIST_ID).unwrap(), None);
+    for table in ["releases", "release_details", "tracks", "tracks_2_releases"] {
+        assert_eq!(count(&app, table), 0, "{table}");
+    }
+}
+
+#[tokio::test]
+async fn loads_artists_by_name() {
+    let app = helpers::mk_app_with_artists(3);
+
+    // AVRALIZE 1 and AVRALIZE 2 don't have the exact name.
+    app.load_artist_by_name("avralize", |_| panic!("shouldn't need to choose"))
+        .await
+        .unwrap();
+    assert!(artists::is_followed(app.db(), ARTIST_ID).unwrap());
+
+    app.load_artist_by_name("avralize ", |_| unreachable!())
+        .await
+        .unwrap();
+
+    app.load_artist_by_name("AVRALIZE 2", |_| unreachable!())
+        .await
+        .unwrap();
+    assert!(artists::is_followed(app.db(), ARTIST_ID + 2).unwrap());
+
+    let mut candidates = vec![];
+    app.load_artist_by_name("avra", |found| {
+        candidates = found.iter().map(|a| a.id).collect();
+        Ok(1)
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        candidates,
+        (0..3).map(|i| ARTIST_ID + i).collect::<Vec<_>>()
+    );
+    assert!(artists::is_followed(app.db(), ARTIST_ID + 1).unwrap());
+
+    assert!(
+        app.load_artist_by_name("nobody", |_| unreachable!())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn loads_artists_from_a_list() {
+    let app = helpers::mk_app_with_artists(2);
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    let list = format!(
+        "# Metal
+{ARTIST_ID}
+https://play.qobuz.com/artist/{}  # the other one
+
+not an artist
+999
+",
+        ARTIST_ID + 1
+    );
+    let result = app.load_artists(&list).await;
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "2 of the listed artists couldn't be loaded"
+    );
+    assert!(artists::is_followed(app.db(), ARTIST_ID + 1).unwrap());
+    assert_eq!(artists::get_all(app.db()).unwrap().len(), 2);
+
+    app.load_artists(&format!("{ARTIST_ID}\n{}", ARTIST_ID + 1))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn imports_follows_with_conflicts() {
+    let app = helpers::mk_app_with_artists(2);
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    let list = FollowList::from_json(&format!(
+        r#"{{
+            "version": 1,
+            "artists": [
+                {{ "id": {ARTIST_ID}, "name": "Someone Else" }},
+                {{ "id": {}, "name": "AVRALIZE 1", "followed_at": "2024-01-02 03:04:05" }}
+            ]
+        }}"#,
+        ARTIST_ID + 1
+    ))
+    .unwrap();
+    app.import_follows(&list).await.unwrap();
+
+    let follows = artists::get_follows(app.db()).unwrap();
+    assert_eq!(follows.len(), 2);
+    assert_eq!(follows[0].artist.name, "AVRALIZE");
+    assert_eq!(follows[1].artist.id, ARTIST_ID + 1);
+    assert_eq!(
+        follows[1].followed_at.as_deref(),
+        Some("2024-01-02 03:04:05")
+    );
+
+    let exported = app.export_follows().unwrap();
+    assert_eq!(
+        FollowList::from_json(&exported.to_json().unwrap()).unwrap(),
+        exported
+    );
+}
+
+#[tokio::test]
+async fn imports_follows_past_missing_artists() {
+    let app = helpers::mk_app_with_artists(2);
+
+    let list = FollowList::from_json(&format!(
+        r#"{{
+            "version": 1,
+            "artists": [
+                {{ "id": 1, "name": "Nobody" }},
+                {{ "id": {}, "name": "AVRALIZE 1" }}
+            ]
+        }}"#,
+        ARTIST_ID + 1
+    ))
+    .unwrap();
+    let error = app.import_follows(&list).await.unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "1 of the imported artists and labels couldn't be loaded"
+    );
+    assert!(artists::is_followed(app.db(), ARTIST_ID + 1).unwrap());
+    assert_eq!(artists::get_all(app.db()).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn imports_followed_labels() {
+    let app = helpers::mk_app();
+    app.catalog()
+        .set_label(LABEL_ID, "Arising Empire", vec![older_label_release()]);
+    app.catalog().set_label(LABEL_ID + 1, "Sharptone", vec![]);
+    app.catalog().set_label(LABEL_ID + 2, "Sumerian", vec![]);
+    app.follow_label(LABEL_ID + 1).await.unwrap();
+
+    let list = FollowList::from_json(&format!(
+        r#"{{
+            "version": 2,
+            "artists": [],
+            "labels": [
+                {{ "id": {LABEL_ID}, "name": "Arising Empire", "followed_at": "2024-01-02 03:04:05" }},
+                {{ "id": {}, "name": "SharpTone Records" }}
+            ]
+        }}"#,
+        LABEL_ID + 1
+    ))
+    .unwrap();
+    app.import_follows(&list).await.unwrap();
+
+    let exported = app.export_follows().unwrap();
+    let labels = exported
+        .labels
+        .iter()
+        .map(|l| (l.id, l.name.as_str()))
+        .collect::<Vec<_>>();
+    // The conflicting label keeps its name here.
+    assert_eq!(
+        labels,
+        [(LABEL_ID, "Arising Empire"), (LABEL_ID + 1, "Sharptone")]
+    );
+    assert_eq!(
+        exported.labels[0].followed_at.as_deref(),
+        Some("2024-01-02 03:04:05")
+    );
+    let label_releases = releases::get_all_for_label(app.db(), LABEL_ID).unwrap();
+    assert_eq!(label_releases[0].id, "older");
+}
+
+#[test]
+fn refuses_newer_follow_lists() {
+    let error = FollowList::from_json(r#"{ "version": 3, "artists": [], "labels": [] }"#)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "the follow list is in format version 3, but this build only knows up to version 2"
+    );
+    assert!(FollowList::from_json("[]").is_err());
+}
+
+fn favorite(artist_id: u32) -> ArtistSummary {
+    ArtistSummary {
+        id: artist_id,
+        name: format!("Artist {artist_id}"),
+        albums_count: None,
+        genre: None,
+    }
+}
+
+#[tokio::test]
+async fn imports_and_syncs_favorites() {
+    let app = helpers::mk_app_with_artists(3);
+    app.catalog()
+        .set_favorite_artists(vec![favorite(ARTIST_ID), favorite(ARTIST_ID + 1)]);
+
+    app.import_favorites(false, false).await.unwrap();
+    let followed = |app: &qobuz::App<qobuz::api::InMemoryCatalog>| {
+        let mut ids = artists::get_all(app.db())
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    };
+    assert_eq!(followed(&app), [ARTIST_ID, ARTIST_ID + 1]);
+
+    app.catalog()
+        .set_favorite_artists(vec![favorite(ARTIST_ID + 1), favorite(ARTIST_ID + 2)]);
+
+    // Without syncing, nobody is unfollowed.
+    app.import_favorites(false, false).await.unwrap();
+    assert_eq!(followed(&app), [ARTIST_ID, ARTIST_ID + 1, ARTIST_ID + 2]);
+
+    app.import_favorites(true, true).await.unwrap();
+    assert_eq!(followed(&app), [ARTIST_ID + 1, ARTIST_ID + 2]);
+    assert!(artists::get_by_id(app.db(), ARTIST_ID).unwrap().is_some());
+
+    // An empty favorites list is more likely the wrong account.
+    app.catalog().set_favorite_artists(vec![]);
+    assert!(app.import_favorites(true, false).await.is_err());
+    assert_eq!(followed(&app), [ARTIST_ID + 1, ARTIST_ID + 2]);
+}
+
+#[tokio::test]
+async fn favorites_that_fail_to_load_stop_the_sync() {
+    let app = helpers::mk_app_with_artists(2);
+    app.load_artist(ARTIST_ID).await.unwrap();
+    // There's no page for the second favorite.
+    app.catalog()
+        .set_favorite_artists(vec![favorite(ARTIST_ID + 1), favorite(ARTIST_ID + 5)]);
+
+    assert!(app.import_favorites(true, false).await.is_err());
+
+    // The other favorite is still imported, but nobody is unfollowed.
+    assert!(artists::is_followed(app.db(), ARTIST_ID).unwrap());
+    assert!(artists::is_followed(app.db(), ARTIST_ID + 1).unwrap());
+}
+
+const LABEL_ID: u64 = 1_016_219;
+
+/// A release the label put out before it was followed.
+fn older_label_release() -> AlbumPage {
+    let mut album = helpers::album_page();
+    album.id = "older".to_string();
+    album.title = "Older".to_string();
+    album
+}
+
+#[tokio::test]
+async fn checks_followed_labels() {
+    let app = helpers::mk_app();
+    app.catalog()
+        .set_label(LABEL_ID, "Arising Empire", vec![older_label_release()]);
+    app.follow_label(LABEL_ID).await.unwrap();
+    assert_eq!(
+        labels::get_by_id(app.db(), LABEL_ID).unwrap().as_deref(),
+        Some("Arising Empire")
+    );
+
+    let found = app.check_labels(&CheckOptions::default()).await.unwrap();
+    assert!(found.is_empty());
+
+    app.catalog().set_label(
+        LABEL_ID,
+        "Arising Empire",
+        vec![older_label_release(), helpers::album_page()],
+    );
+    let found = app.check_labels(&CheckOptions::default()).await.unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].label.name, "Arising Empire");
+    let release_ids = found[0]
+        .releases
+        .iter()
+        .map(|r| r.id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(release_ids, [NEW_RELEASE_ID]);
+
+    // The label's new release goes in the playlist like an artist's would.
+    let playlist = app.gen_playlist(&PlaylistOptions::default()).await.unwrap();
+    assert!(playlist.is_some());
+    assert_eq!(app.catalog().playlists()[0].track_ids.len(), 3);
+}
+
+#[tokio::test]
+async fn label_checks_skip_releases_found_for_artists() {
+    let app = helpers::mk_app();
+    app.catalog().set_label(LABEL_ID, "Arising Empire", vec![]);
+    app.follow_label(LABEL_ID).await.unwrap();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+
+    app.catalog()
+        .set_label(LABEL_ID, "Arising Empire", vec![helpers::album_page()]);
+    let found = app.check_labels(&CheckOptions::default()).await.unwrap();
+
+    assert!(found.is_empty());
+    let label_releases = releases::get_all_for_label(app.db(), LABEL_ID).unwrap();
+    assert_eq!(label_releases[0].id, NEW_RELEASE_ID);
+}
+
+#[tokio::test]
+async fn artists_fill_in_label_release_types() {
+    let mut album = helpers::album_page();
+    album.id = "hw971g1ngzxca".to_string();
+    let app = helpers::mk_app();
+    app.catalog()
+        .set_label(LABEL_ID, "Arising Empire", vec![album]);
+    app.follow_label(LABEL_ID).await.unwrap();
+    app.load_artist(ARTIST_ID).await.unwrap();
+
+    let stored = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    let release = stored.iter().find(|r| r.id == "hw971g1ngzxca").unwrap();
+    assert_eq!(release.release_type, ReleaseType::Album);
+}
+
+#[tokio::test]
+async fn artist_checks_skip_releases_found_for_labels() {
+    let app = helpers::mk_app();
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.catalog().set_label(LABEL_ID, "Arising Empire", vec![]);
+    app.follow_label(LABEL_ID).await.unwrap();
+    app.catalog()
+        .set_label(LABEL_ID, "Arising Empire", vec![helpers::album_page()]);
+    let found = app.check_labels(&CheckOptions::default()).await.unwrap();
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+
+    assert!(found.is_empty());
+    let stored = releases::get_all_for_artist(app.db(), ARTIST_ID).unwrap();
+    let release = stored.iter().find(|r| r.id == NEW_RELEASE_ID).unwrap();
+    assert_eq!(release.release_type, ReleaseType::EpSingle);
+}
+
+#[tokio::test]
+async fn retries_shared_releases_once() {
+    let app = helpers::mk_app_with_artists(2);
+    app.load_artist(ARTIST_ID).await.unwrap();
+    app.load_artist(ARTIST_ID + 1).await.unwrap();
+
+    // Both artists put out the release before its tracks turn up.
+    let mut album = helpers::album_page();
+    album.tracks.items.clear();
+    app.catalog().add_release(album);
+    app.check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        1
+    );
+
+    let no_wait = CheckOptions {
+        pending: PendingRetry {
+            base_delay: Duration::ZERO,
+            ..PendingRetry::default()
+        },
+        ..CheckOptions::default()
+    };
+    app.check_for_new_releases(&no_wait).await.unwrap();
+    assert_eq!(
+        pending::get_all(app.db()).unwrap()[NEW_RELEASE_ID].attempts,
+        2
+    );
+
+    // Once the tracks turn up, it's only reported the once.
+    app.catalog().add_release(helpers::album_page());
+    let found = app.check_for_new_releases(&no_wait).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].artist.id, ARTIST_ID);
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+}
+
+#[tokio::test]
+async fn concurrent_check_keeps_artist_order() {
+    let app = helpers::mk_app_with_artists(5);
+    for i in 0..5 {
+        app.load_artist(ARTIST_ID + i).await.unwrap();
+    }
+
+    let found = app
+        .check_for_new_releases(&CheckOptions {
+            concurrency: 3,
+            ..CheckOptions::default()
+        })
+        .await
+        .unwrap();
+
+    let artist_ids = found.iter().map(|f| f.artist.id).collect::<Vec<_>>();
+    assert_eq!(
+        artist_ids,
+        (0..5).map(|i| ARTIST_ID + i).collect::<Vec<_>>()
+    );
+    assert!(found.iter().all(|f| f.releases.len() == 1));
+}
+
+#[tokio::test]
+async fn pages_through_artist_releases() {
+    let app = helpers::mk_app();
+    app.catalog()
+        .add_artist_releases(ARTIST_ID, ReleaseType::EpSingle, helpers::all_ep_singles());
+
+    app.load_artist(ARTIST_ID).await.unwrap();
+    let release_ids = releases::get_all_for_artist(app.db(), ARTIST_ID)
+        .unwrap()
+        .into_iter()
+        .map(|r| r.id)
+        .collect::<Vec<_>>();
+    assert_eq!(release_ids.len(), 9);
+    assert!(release_ids.contains(&"zxyt7bi4o2mpa".to_string()));
+
+    // The older releases are already known, so only the new one is found.
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].releases.len(), 1);
+    assert_eq!(found[0].releases[0].id, NEW_RELEASE_ID);
+}
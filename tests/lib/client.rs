@@ -0,0 +1,284 @@
+use std::time::{Duration, Instant};
+
+use qobuz::api::{ApiError, Cassette, Catalog, RateLimit, ResponseCache};
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers};
+
+use crate::helpers::{self, ARTIST_ID};
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::artist_page_json()))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let page = api.get_artist_page(ARTIST_ID).await.unwrap();
+
+    assert_eq!(page.id, ARTIST_ID);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn refuses_long_retry_after() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "86400"))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let started = Instant::now();
+    let err = api.get_artist_page(ARTIST_ID).await.unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    let err = err.downcast_ref::<ApiError>().unwrap();
+    assert!(matches!(
+        err,
+        ApiError::RateLimited {
+            retry_after: Some(wait),
+            ..
+        } if *wait == Duration::from_hours(24)
+    ));
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let result = api.get_artist_page(ARTIST_ID).await;
+
+    assert!(result.is_err());
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn does_not_retry_playlist_creation() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/playlist/create"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let result = api.create_playlist("test", vec![1, 2, 3]).await;
+
+    assert!(result.is_err());
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limits_requests() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::artist_page_json()))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server).with_rate_limit(Some(RateLimit {
+        requests_per_second: 20.0,
+        burst: 2,
+    }));
+
+    let start = Instant::now();
+    for _ in 0..6 {
+        api.get_artist_page(ARTIST_ID).await.unwrap();
+    }
+
+    // The burst goes out straight away, then one request every 50ms.
+    assert!(start.elapsed() >= Duration::from_millis(190));
+}
+
+#[tokio::test]
+async fn gets_all_tracks_for_long_releases() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/album/get"))
+        .and(matchers::query_param("album_id", "bx7hq2mn0c4ra"))
+        .respond_with(helpers::AlbumPages::box_set())
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let release = api.get_release("bx7hq2mn0c4ra").await.unwrap().unwrap();
+    let tracks = release.tracks.items;
+
+    assert_eq!(tracks.len(), 120);
+    assert_eq!(tracks[0].title, "Session 1");
+    assert_eq!(tracks[119].title, "Session 120");
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn expired_tokens_are_unauthorized() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({
+            "status": "error",
+            "code": 401,
+            "message": "User authentication is required.",
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let err = api.get_artist_page(ARTIST_ID).await.unwrap_err();
+
+    let err = err.downcast_ref::<ApiError>().unwrap();
+    assert!(matches!(err, ApiError::Unauthorized { .. }));
+    assert_eq!(err.exit_code(), 3);
+}
+
+#[tokio::test]
+async fn unexpected_responses_are_decode_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": 1})))
+        .mount(&mock_server)
+        .await;
+
+    let api = helpers::mk_api(&mock_server);
+    let err = api.get_artist_page(ARTIST_ID).await.unwrap_err();
+
+    let err = err.downcast_ref::<ApiError>().unwrap();
+    assert!(matches!(err, ApiError::Decode { .. }));
+    assert_eq!(err.exit_code(), 8);
+}
+
+#[tokio::test]
+async fn caches_responses() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::artist_page_json()))
+        .mount(&mock_server)
+        .await;
+
+    let cache = ResponseCache::open(":memory:").unwrap();
+    let api = helpers::mk_api(&mock_server).with_cache(Some(cache));
+    let first = api.get_artist_page(ARTIST_ID).await.unwrap();
+    let second = api.get_artist_page(ARTIST_ID).await.unwrap();
+
+    assert_eq!(first.name.display, second.name.display);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn does_not_cache_releases_that_are_not_ready() {
+    let mut not_streamable = helpers::album_page_json();
+    not_streamable["streamable"] = false.into();
+    let mut no_tracks = helpers::album_page_json();
+    no_tracks["id"] = "no-tracks".into();
+    no_tracks["tracks"]["items"] = serde_json::json!([]);
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/album/get"))
+        .and(matchers::query_param("album_id", "na99v5xa7s26a"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(not_streamable))
+        .mount(&mock_server)
+        .await;
+    Mock::given(matchers::path("/album/get"))
+        .and(matchers::query_param("album_id", "no-tracks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(no_tracks))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(matchers::path("/album/get"))
+        .and(matchers::query_param("album_id", "ready"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::album_page_json()))
+        .mount(&mock_server)
+        .await;
+
+    let cache = ResponseCache::open(":memory:").unwrap();
+    let api = helpers::mk_api(&mock_server).with_cache(Some(cache));
+    for release_id in ["na99v5xa7s26a", "no-tracks", "ready"] {
+        api.get_release(release_id).await.unwrap();
+        api.get_release(release_id).await.unwrap();
+    }
+
+    // Only the ready release is served from the cache the second time.
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn revalidates_stale_responses() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .and(matchers::header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .set_body_json(helpers::artist_page_json()),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let cache = ResponseCache::open(":memory:")
+        .unwrap()
+        .with_ttl("artist/page", Duration::ZERO);
+    let api = helpers::mk_api(&mock_server).with_cache(Some(cache));
+    api.get_artist_page(ARTIST_ID).await.unwrap();
+    let page = api.get_artist_page(ARTIST_ID).await.unwrap();
+
+    assert_eq!(page.id, ARTIST_ID);
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].headers.contains_key("If-None-Match"));
+}
+
+#[tokio::test]
+async fn records_and_replays_cassettes() {
+    let mock_server = MockServer::start().await;
+    Mock::given(matchers::path("/artist/page"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(helpers::artist_page_json()))
+        .mount(&mock_server)
+        .await;
+    Mock::given(matchers::path("/user/login"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "user": { "id": 1, "email": "me@example.com" },
+            "user_auth_token": "secret-token",
+        })))
+        .mount(&mock_server)
+        .await;
+    let dir = helpers::TempDir::new();
+
+    let cassette = Cassette::record_to(dir.path()).unwrap();
+    let api = helpers::mk_api(&mock_server).with_cassette(Some(cassette));
+    let recorded = api.get_artist_page(ARTIST_ID).await.unwrap();
+    api.login("me@example.com", "hunter2").await.unwrap();
+
+    // The cassette is written once, when it's dropped along with the Api.
+    let cassette_path = dir.path().join("cassette.json");
+    assert!(!cassette_path.exists());
+    drop(api);
+    let cassette_json = std::fs::read_to_string(&cassette_path).unwrap();
+    assert!(!cassette_json.contains("secret-token"));
+    assert!(!cassette_json.contains("me@example.com"));
+
+    // Nothing is listening here, so everything has to come from the cassette.
+    let cassette = Cassette::replay_from(dir.path()).unwrap();
+    let api = helpers::mk_api(&mock_server)
+        .with_api_base("http://127.0.0.1:9")
+        .with_cassette(Some(cassette));
+    let replayed = api.get_artist_page(ARTIST_ID).await.unwrap();
+    let login = api.login("someone@example.com", "password").await.unwrap();
+
+    assert_eq!(recorded.name.display, replayed.name.display);
+    assert_eq!(login.user_auth_token, "REDACTED");
+    assert!(api.get_artist_page(1).await.is_err());
+}
@@ -0,0 +1,109 @@
+use qobuz::{
+    App, Db,
+    api::InMemoryCatalog,
+    app::CheckOptions,
+    data::{db, releases, tracks},
+};
+
+use crate::helpers::{self, ARTIST_ID, NEW_RELEASE_ID};
+
+const BASELINE: &str = include_str!("fixtures/baseline.sql");
+
+fn baseline_db() -> Db {
+    let db = Db::new(":memory:").unwrap();
+    db.conn.execute_batch(BASELINE).unwrap();
+    db
+}
+
+#[test]
+fn upgrades_baseline_database() {
+    let db = baseline_db();
+    assert_eq!(db::schema_version(&db).unwrap(), 0);
+
+    let applied = db::migrate(&db).unwrap();
+
+    assert_eq!(applied.len(), db::MIGRATIONS.len());
+    assert_eq!(db::schema_version(&db).unwrap(), db::latest_version());
+    assert!(db::pending_migrations(&db).unwrap().is_empty());
+
+    // Existing data survives, with the new columns filled in.
+    let all_releases = releases::get_all_for_artist(&db, ARTIST_ID).unwrap();
+    assert_eq!(all_releases.len(), 2);
+    assert!(
+        all_releases
+            .iter()
+            .all(|r| r.class.is_none() && !r.upcoming)
+    );
+    assert!(tracks::get_latest(&db, &[]).unwrap().is_empty());
+
+    // Nothing left to do.
+    assert!(db::migrate(&db).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn upgraded_database_works() {
+    let app = App::new(baseline_db(), helpers::mk_catalog(2)).unwrap();
+    let other_artist_id = ARTIST_ID + 1;
+    app.load_artist(other_artist_id).await.unwrap();
+
+    let found = app
+        .check_for_new_releases(&CheckOptions::default())
+        .await
+        .unwrap();
+
+    // The new release was already loaded for the first artist before the
+    // upgrade, so it's linked to the other one rather than found again.
+    assert!(found.is_empty());
+    let other_releases = releases::get_all_for_artist(app.db(), other_artist_id).unwrap();
+    assert!(other_releases.iter().any(|r| r.id == NEW_RELEASE_ID));
+}
+
+#[test]
+fn upgrades_databases_with_some_changes_already() {
+    // Builds from before migrations existed added columns as they went.
+    let db = baseline_db();
+    db.conn
+        .execute_batch("alter table releases add column class text;")
+        .unwrap();
+
+    db::migrate(&db).unwrap();
+
+    assert_eq!(db::schema_version(&db).unwrap(), db::latest_version());
+}
+
+#[test]
+fn refuses_newer_databases() {
+    let db = Db::new(":memory:").unwrap();
+    db.conn
+        .pragma_update(None, "user_version", db::latest_version() + 1)
+        .unwrap();
+
+    assert!(db::migrate(&db).is_err());
+    assert!(App::new(db, InMemoryCatalog::new()).is_err());
+}
+
+#[test]
+fn waits_for_other_connections() {
+    let dir = helpers::TempDir::new();
+    let path = dir.path().join("music.db3");
+    let path = path.to_str().unwrap();
+    let db = Db::new(path).unwrap();
+    db::init(&db).unwrap();
+
+    // Another connection, like the response cache's, is part way through a
+    // write.
+    let other = Db::new(path).unwrap();
+    other.conn.execute_batch("begin immediate;").unwrap();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        other.conn.execute_batch("commit;").unwrap();
+    });
+
+    db.conn
+        .execute(
+            "insert into playlists (id, name) values (1, 'playlist');",
+            (),
+        )
+        .unwrap();
+    writer.join().unwrap();
+}
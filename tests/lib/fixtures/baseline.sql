@@ -0,0 +1,83 @@
+-- A database created by the release before schema migrations existed, at
+-- user_version 0.
+
+create table if not exists artists (
+    id integer primary key,
+    name text not null
+);
+
+create table if not exists releases (
+    id text primary key,
+    title text not null,
+    created_at timestamp default (datetime('now', 'localtime')) not null,
+    release_type_id text not null,
+    verified boolean not null default false,
+    foreign key (release_type_id) references release_type (variant)
+);
+
+create table if not exists artists_2_releases (
+    artist_id integer not null,
+    release_id text not null,
+    primary key (artist_id, release_id),
+    foreign key (artist_id) references artists (id),
+    foreign key (release_id) references releases (id)
+);
+
+create table if not exists tracks (
+    id integer primary key,
+    title text not null
+);
+
+create table if not exists tracks_2_releases (
+    release_id text not null,
+    track_id integer not null,
+    primary key (release_id, track_id),
+    foreign key (track_id) references tracks (id),
+    foreign key (release_id) references releases (id)
+);
+
+create table if not exists release_type (
+    variant text primary key
+);
+
+insert into release_type (variant) values
+    ('Album'),
+    ('Compilation'),
+    ('Download'),
+    ('EpSingle'),
+    ('Live'),
+    ('AwardedReleases'),
+    ('Other')
+on conflict (variant) do nothing;
+
+create table if not exists playlists (
+    id integer primary key,
+    name text not null,
+    created_at timestamp default (datetime('now', 'localtime')) not null
+);
+
+create index if not exists playlists__created_at on playlists (created_at);
+
+
+insert into artists (id, name) values (13925362, 'AVRALIZE');
+
+insert into releases (id, title, created_at, release_type_id, verified) values
+    ('hw971g1ngzxca', 'FREAKS', '2025-01-01 09:00:00', 'Album', false),
+    ('na99v5xa7s26a', 'helium', '2025-05-10 09:00:00', 'EpSingle', true);
+
+insert into artists_2_releases (artist_id, release_id) values
+    (13925362, 'hw971g1ngzxca'),
+    (13925362, 'na99v5xa7s26a');
+
+insert into tracks (id, title) values
+    (333336241, 'helium'),
+    (333336242, 'NOTHING'),
+    (333336243, 'paralyzed');
+
+insert into tracks_2_releases (release_id, track_id) values
+    ('na99v5xa7s26a', 333336241),
+    ('na99v5xa7s26a', 333336242),
+    ('na99v5xa7s26a', 333336243);
+
+insert into playlists (id, name, created_at) values
+    (32500402, '2025-05-10', '2025-05-10 09:05:00');
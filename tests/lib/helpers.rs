@@ -0,0 +1,180 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use qobuz::{
+    Api, App, Db,
+    api::{
+        InMemoryCatalog, RetryPolicy,
+        models::{AlbumPage, ArtistName, ArtistPage, Release, ReleaseList, SimilarArtist},
+    },
+    types::ReleaseType,
+};
+use rand::Rng;
+use wiremock::{MockServer, Request, Respond, ResponseTemplate};
+
+const ARTIST_PAGE_0_RESPONSE: &str = include_str!("../cli/responses/artist_page_0.json");
+const ARTIST_PAGE_1_RESPONSE: &str = include_str!("../cli/responses/artist_page_1.json");
+const ARTIST_RELEASES_EP_SINGLE_RESPONSE: &str =
+    include_str!("../cli/responses/artist_releases_ep_single.json");
+const ALBUM_PAGE_RESPONSE: &str = include_str!("../cli/responses/album_page.json");
+const ALBUM_PAGE_BOX_SET_RESPONSE: &str = include_str!("../cli/responses/album_page_box_set.json");
+
+pub const ARTIST_ID: u32 = 13_925_362;
+pub const NEW_RELEASE_ID: &str = "na99v5xa7s26a";
+
+/// An App backed by an in-memory database and catalog. The catalog serves
+/// the artist's page, then an updated page with one new release.
+pub fn mk_app() -> App<InMemoryCatalog> {
+    mk_app_with_artists(1)
+}
+
+/// Like [`mk_app`], but with `n` copies of the artist, numbered from
+/// [`ARTIST_ID`].
+pub fn mk_app_with_artists(n: u32) -> App<InMemoryCatalog> {
+    let db = Db::new(":memory:").unwrap();
+    App::new(db, mk_catalog(n)).unwrap()
+}
+
+/// The catalog behind [`mk_app_with_artists`].
+pub fn mk_catalog(n: u32) -> InMemoryCatalog {
+    let catalog = InMemoryCatalog::new();
+    for i in 0..n {
+        for json in [ARTIST_PAGE_0_RESPONSE, ARTIST_PAGE_1_RESPONSE] {
+            let mut page = artist_page(json);
+            page.id = ARTIST_ID + i;
+            if i > 0 {
+                page.name.display = format!("{} {i}", page.name.display);
+            }
+            catalog.add_artist_page(page);
+        }
+    }
+
+    catalog.add_release(album_page());
+    catalog
+}
+
+/// The new release the artist puts out between checks.
+pub fn album_page() -> AlbumPage {
+    serde_json::from_str(ALBUM_PAGE_RESPONSE).unwrap()
+}
+
+fn artist_page(json: &str) -> ArtistPage {
+    serde_json::from_str(json).unwrap()
+}
+
+/// The artist's page under another id and name, listing `similar` artists.
+pub fn artist_page_with_similar(id: u32, name: &str, similar: &[(u32, &str)]) -> ArtistPage {
+    let mut page = artist_page(ARTIST_PAGE_0_RESPONSE);
+    page.id = id;
+    page.name.display = name.to_string();
+    page.similar_artists.items = similar
+        .iter()
+        .map(|&(id, name)| SimilarArtist {
+            id,
+            name: ArtistName {
+                display: name.to_string(),
+            },
+        })
+        .collect();
+    page
+}
+
+/// All of the artist's EPs and singles, including the older ones that don't
+/// fit on the artist page.
+pub fn all_ep_singles() -> Vec<Release> {
+    let page = artist_page(ARTIST_PAGE_1_RESPONSE);
+    let release_list =
+        serde_json::from_str::<ReleaseList>(ARTIST_RELEASES_EP_SINGLE_RESPONSE).unwrap();
+
+    page.releases
+        .into_iter()
+        .filter(|rels| rels.release_type == ReleaseType::EpSingle)
+        .flat_map(|rels| rels.items)
+        .chain(release_list.items)
+        .collect()
+}
+
+/// An Api pointed at a mock server that doesn't wait around between
+/// requests.
+pub fn mk_api(mock_server: &MockServer) -> Api {
+    Api::new(Some("token"), "app")
+        .unwrap()
+        .with_api_base(mock_server.uri())
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_retry_after: Duration::from_secs(1),
+        })
+        .with_rate_limit(None)
+}
+
+pub fn artist_page_json() -> serde_json::Value {
+    serde_json::from_str(ARTIST_PAGE_0_RESPONSE).unwrap()
+}
+
+pub fn album_page_json() -> serde_json::Value {
+    serde_json::from_str(ALBUM_PAGE_RESPONSE).unwrap()
+}
+
+/// Serves a release a page of tracks at a time, like `album/get` does.
+pub struct AlbumPages(serde_json::Value);
+
+impl AlbumPages {
+    /// A 120 track box set.
+    pub fn box_set() -> Self {
+        Self(serde_json::from_str(ALBUM_PAGE_BOX_SET_RESPONSE).unwrap())
+    }
+}
+
+impl Respond for AlbumPages {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let param = |name: &str| {
+            request
+                .url
+                .query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.parse::<usize>().unwrap())
+        };
+        let offset = param("offset").unwrap_or(0);
+        let limit = param("limit").unwrap_or(50);
+
+        let mut album = self.0.clone();
+        let tracks = &mut album["tracks"];
+        let items = tracks["items"].as_array().unwrap();
+        let page = items.iter().skip(offset).take(limit).cloned().collect();
+        tracks["items"] = serde_json::Value::Array(page);
+        tracks["offset"] = offset.into();
+        tracks["limit"] = limit.into();
+
+        ResponseTemplate::new(200).set_body_json(album)
+    }
+}
+
+/// A directory that's removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let name = rand::rng()
+            .sample_iter(rand::distr::Alphabetic)
+            .take(10)
+            .map(char::from)
+            .collect::<String>();
+        let path = std::env::temp_dir().join(format!("qobuz-{name}"));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir_all(&self.0);
+    }
+}
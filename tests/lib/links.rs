@@ -0,0 +1,109 @@
+use qobuz::types::{LinkError, QobuzLink};
+
+#[test]
+fn parses_artist_links() {
+    for link in [
+        "https://play.qobuz.com/artist/1373166",
+        "https://open.qobuz.com/artist/1373166",
+        "https://open.qobuz.com/artist/1373166?si=7d0f3a2b",
+        "https://www.qobuz.com/gb-en/interpreter/avralize/1373166",
+        "https://www.qobuz.com/fr-fr/interpreter/avralize/1373166/",
+        " https://qobuz.com/interpreter/avralize/1373166 ",
+    ] {
+        assert_eq!(
+            QobuzLink::parse(link),
+            Ok(QobuzLink::Artist(1_373_166)),
+            "{link}"
+        );
+    }
+}
+
+#[test]
+fn parses_album_links() {
+    for link in [
+        "https://play.qobuz.com/album/na99v5xa7s26a",
+        "https://open.qobuz.com/album/na99v5xa7s26a",
+        "https://www.qobuz.com/gb-en/album/helium-avralize/na99v5xa7s26a",
+        "https://www.qobuz.com/us-en/album/helium-avralize/na99v5xa7s26a#tracks",
+    ] {
+        assert_eq!(
+            QobuzLink::parse(link),
+            Ok(QobuzLink::Album("na99v5xa7s26a".to_string())),
+            "{link}"
+        );
+    }
+}
+
+#[test]
+fn parses_label_links() {
+    for link in [
+        "https://play.qobuz.com/label/1016219",
+        "https://www.qobuz.com/gb-en/label/arising-empire/download-streaming-albums/1016219",
+    ] {
+        assert_eq!(
+            QobuzLink::parse(link),
+            Ok(QobuzLink::Label(1_016_219)),
+            "{link}"
+        );
+    }
+    assert_eq!(QobuzLink::label_id("1016219"), Ok(1_016_219));
+    assert!(QobuzLink::label_id("https://play.qobuz.com/artist/1373166").is_err());
+}
+
+#[test]
+fn rejects_other_links() {
+    let link = "https://play.qobuz.com/playlist/12345";
+    assert_eq!(
+        QobuzLink::parse(link),
+        Err(LinkError::Unsupported {
+            kind: "playlist".to_string(),
+            link: link.to_string(),
+        })
+    );
+    for link in [
+        "https://www.qobuz.com/gb-en/interpreter/avralize",
+        "https://www.qobuz.com/gb-en/album/helium-avralize",
+        "https://qobuz.com/album/helium",
+        "https://play.qobuz.com/",
+    ] {
+        assert_eq!(
+            QobuzLink::parse(link),
+            Err(LinkError::MissingId(link.to_string()))
+        );
+    }
+    for link in [
+        "avralize",
+        "https://example.com/artist/1373166",
+        "ftp://play.qobuz.com/artist/1",
+    ] {
+        assert_eq!(
+            QobuzLink::parse(link),
+            Err(LinkError::NotQobuz(link.to_string()))
+        );
+    }
+}
+
+#[test]
+fn parses_ids_or_links() {
+    assert_eq!(QobuzLink::artist_id(" 1373166 "), Ok(1_373_166));
+    assert_eq!(
+        QobuzLink::artist_id("https://play.qobuz.com/artist/1373166"),
+        Ok(1_373_166)
+    );
+    assert_eq!(
+        QobuzLink::artist_id("https://play.qobuz.com/album/na99v5xa7s26a")
+            .unwrap_err()
+            .to_string(),
+        "expected a link to an artist, but 'https://play.qobuz.com/album/na99v5xa7s26a' is a link to an album"
+    );
+
+    assert_eq!(
+        QobuzLink::album_id("na99v5xa7s26a").unwrap(),
+        "na99v5xa7s26a"
+    );
+    assert_eq!(
+        QobuzLink::album_id("https://open.qobuz.com/album/na99v5xa7s26a").unwrap(),
+        "na99v5xa7s26a"
+    );
+    assert!(QobuzLink::album_id("https://play.qobuz.com/artist/1373166").is_err());
+}
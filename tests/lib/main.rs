@@ -0,0 +1,5 @@
+mod app;
+mod client;
+mod db;
+mod helpers;
+mod links;